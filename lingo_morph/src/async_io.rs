@@ -0,0 +1,228 @@
+use std::{future, io, pin::Pin};
+
+use futures_io::AsyncRead;
+
+use crate::{
+    context::{self, Contextual, Diagnosed, Diagnostic, ProcessingFailed, Streaming},
+    processed,
+    source::{ExpiredSnapshot, Source},
+    Processor,
+};
+
+const CHUNK: usize = 8 * 1024;
+
+// Pulls bytes from the reader whenever the processor reports it needs more of them
+pub struct AsyncStream<R, P> {
+    reader: R,
+    stream: Streaming<P>,
+    chunk: Box<[u8]>,
+}
+
+impl<R, P> AsyncStream<R, P>
+where
+    R: AsyncRead + Unpin,
+    P: Processor<u8>,
+{
+    pub fn new(reader: R, processor: P) -> Self {
+        Self::with_chunk_size(reader, processor, CHUNK)
+    }
+
+    pub fn with_chunk_size(reader: R, processor: P, size: usize) -> Self {
+        Self {
+            reader,
+            stream: Streaming::new(processor),
+            chunk: vec![0; size.max(1)].into_boxed_slice(),
+        }
+    }
+
+    // None once the reader is drained and every buffered byte was processed
+    pub async fn next(&mut self) -> Option<Result<P::Output, processed::Error>> {
+        loop {
+            if let Some(next) = self.stream.step().transpose() {
+                return Some(next);
+            }
+            if self.stream.is_exhausted() {
+                return None;
+            }
+            match read(&mut self.reader, &mut self.chunk).await {
+                Ok(0) => {
+                    self.stream.finish();
+                }
+                Ok(read) => {
+                    self.stream.feed(&self.chunk[..read]);
+                }
+                Err(error) => return Some(Err(error.into())),
+            }
+        }
+    }
+
+    pub async fn collect(mut self) -> Result<Vec<P::Output>, processed::Error> {
+        let mut outputs = Vec::new();
+        while let Some(next) = self.next().await {
+            outputs.push(next?);
+        }
+        Ok(outputs)
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        self.stream.diagnostics()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+async fn read<R>(reader: &mut R, chunk: &mut [u8]) -> io::Result<usize>
+where
+    R: AsyncRead + Unpin,
+{
+    loop {
+        match future::poll_fn(|cx| Pin::new(&mut *reader).poll_read(cx, chunk)).await {
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => (),
+            read => return read,
+        }
+    }
+}
+
+// The bytes read from the reader so far, partial until the reader is drained
+pub struct AsyncSource<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    // Offset of the first buffered byte, snapshots count from the start of the input
+    base: usize,
+    idx: usize,
+    finished: bool,
+    chunk: Box<[u8]>,
+}
+
+impl<R> AsyncSource<R>
+where
+    R: AsyncRead + Unpin,
+{
+    pub fn new(reader: R) -> Self {
+        Self::with_chunk_size(reader, CHUNK)
+    }
+
+    pub fn with_chunk_size(reader: R, size: usize) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            base: 0,
+            idx: 0,
+            finished: false,
+            chunk: vec![0; size.max(1)].into_boxed_slice(),
+        }
+    }
+
+    // Bytes read but not consumed yet
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffer[self.idx..]
+    }
+
+    pub fn offset(&self) -> usize {
+        self.base + self.idx
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // Reads one more chunk, false once the reader is drained
+    pub async fn fill(&mut self) -> io::Result<bool> {
+        let read = read(&mut self.reader, &mut self.chunk).await?;
+        self.buffer.extend_from_slice(&self.chunk[..read]);
+        self.finished = read == 0;
+        Ok(!self.finished)
+    }
+
+    // Runs the processor from the current offset, reading more whenever it's incomplete
+    pub async fn process<P>(
+        &mut self,
+        processor: &mut P,
+    ) -> Result<Diagnosed<P::Output>, ProcessingFailed>
+    where
+        P: Processor<u8>,
+    {
+        // Snapshots taken before are given up, so the consumed bytes don't pile up
+        self.buffer.drain(..self.idx);
+        self.base += self.idx;
+        self.idx = 0;
+        loop {
+            let processed = context::process(processor, Contextual::new(&mut *self));
+            match processed.map_err(|failed| failed.map_cancelled(drop)) {
+                Err(ProcessingFailed::Incomplete(_)) if !self.finished => {
+                    self.idx = 0;
+                    self.fill()
+                        .await
+                        .map_err(|error| ProcessingFailed::DuringProcessing(error.into()))?;
+                }
+                Err(failed) => {
+                    // Nothing is consumed by a failed attempt, the next one starts at the same byte
+                    self.idx = 0;
+                    return Err(failed);
+                }
+                Ok(mut diagnosed) => {
+                    diagnosed.diagnostics = diagnosed
+                        .diagnostics
+                        .into_iter()
+                        .map(|x| {
+                            let message = x.message().to_owned();
+                            Diagnostic::new(x.severity(), message, x.offset() + self.base)
+                        })
+                        .collect();
+                    return Ok(diagnosed);
+                }
+            }
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R> Source for &mut AsyncSource<R> {
+    type Item = u8;
+    type Snapshot = usize;
+    type RollBackErr = ExpiredSnapshot;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = *self.buffer.get(self.idx)?;
+        self.idx += 1;
+        Some(next)
+    }
+
+    #[inline]
+    fn snapshot(&self) -> Self::Snapshot {
+        self.base + self.idx
+    }
+
+    fn roll_back(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        if to < self.base || to > self.base + self.buffer.len() {
+            return Err(ExpiredSnapshot(to));
+        }
+        self.idx = to - self.base;
+        Ok(())
+    }
+
+    #[inline]
+    fn peek(&mut self) -> Option<&Self::Item> {
+        self.buffer.get(self.idx)
+    }
+
+    #[inline]
+    fn peek_mut(&mut self) -> Option<&mut Self::Item> {
+        self.buffer.get_mut(self.idx)
+    }
+
+    #[inline]
+    fn is_partial(&self) -> bool {
+        !self.finished
+    }
+
+    #[inline]
+    fn as_slice(&self) -> Option<&[Self::Item]> {
+        Some(&self.buffer[self.idx..])
+    }
+}
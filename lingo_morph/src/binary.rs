@@ -0,0 +1,390 @@
+use std::{
+    any,
+    error::Error,
+    fmt::{self, Display},
+    marker::PhantomData,
+    mem,
+};
+
+use crate::{
+    context::guarded,
+    done, err,
+    grammar::{Describe, Expr, Grammar},
+    processed::{ran_out, rewind, Needed},
+    source::{Limit, Source},
+    try_done, Processed, Processor, Status,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryError {
+    VarintOverflow,
+    LengthOutOfRange,
+    LengthMismatch { expected: usize, left: usize },
+}
+
+impl Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::VarintOverflow => write!(f, "varint does not fit in 64 bits"),
+            Self::LengthOutOfRange => write!(f, "length prefix does not fit in usize"),
+            Self::LengthMismatch { expected, left } => write!(
+                f,
+                "length prefixed body of {expected} bytes left {left} bytes unprocessed"
+            ),
+        }
+    }
+}
+
+impl Error for BinaryError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+pub trait FromBytes: Sized {
+    const SIZE: usize;
+
+    fn from_be_bytes(bytes: &[u8]) -> Self;
+
+    fn from_le_bytes(bytes: &[u8]) -> Self;
+}
+
+macro_rules! from_bytes {
+    ($($ty:ty),+) => {
+        $(
+            impl FromBytes for $ty {
+                const SIZE: usize = mem::size_of::<$ty>();
+
+                #[inline]
+                fn from_be_bytes(bytes: &[u8]) -> Self {
+                    let mut buffer = [0; mem::size_of::<$ty>()];
+                    buffer.copy_from_slice(bytes);
+                    <$ty>::from_be_bytes(buffer)
+                }
+
+                #[inline]
+                fn from_le_bytes(bytes: &[u8]) -> Self {
+                    let mut buffer = [0; mem::size_of::<$ty>()];
+                    buffer.copy_from_slice(bytes);
+                    <$ty>::from_le_bytes(buffer)
+                }
+            }
+        )+
+    };
+}
+
+from_bytes!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Number<T> {
+    endian: Endian,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Processor<u8> for Number<T>
+where
+    T: FromBytes,
+{
+    type Output = T;
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = u8>,
+    {
+        let fallback = given.snapshot();
+        let mut buffer = [0; 16];
+        for (idx, slot) in buffer.iter_mut().take(T::SIZE).enumerate() {
+            match given.next() {
+                Some(byte) => *slot = byte,
+                None => return ran_out(given, fallback, Needed::Size(T::SIZE - idx)),
+            }
+        }
+        let bytes = &buffer[..T::SIZE];
+        let number = match self.endian {
+            Endian::Big => T::from_be_bytes(bytes),
+            Endian::Little => T::from_le_bytes(bytes),
+        };
+        done(number, given)
+    }
+}
+
+impl<T> Describe for Number<T> {
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        let endian = match self.endian {
+            Endian::Big => "big",
+            Endian::Little => "little",
+        };
+        Expr::special(format!("{} {endian} endian", any::type_name::<T>()))
+    }
+}
+
+pub fn number<T>(endian: Endian) -> Number<T>
+where
+    T: FromBytes,
+{
+    Number {
+        endian,
+        marker: PhantomData,
+    }
+}
+
+pub fn big_endian<T>() -> Number<T>
+where
+    T: FromBytes,
+{
+    number(Endian::Big)
+}
+
+pub fn little_endian<T>() -> Number<T>
+where
+    T: FromBytes,
+{
+    number(Endian::Little)
+}
+
+macro_rules! numbers {
+    ($($ty:ty => $be:ident, $le:ident;)+) => {
+        $(
+            #[inline]
+            pub fn $be() -> Number<$ty> {
+                big_endian()
+            }
+
+            #[inline]
+            pub fn $le() -> Number<$ty> {
+                little_endian()
+            }
+        )+
+    };
+}
+
+numbers! {
+    u16 => u16_be, u16_le;
+    u32 => u32_be, u32_le;
+    u64 => u64_be, u64_le;
+    u128 => u128_be, u128_le;
+    i16 => i16_be, i16_le;
+    i32 => i32_be, i32_le;
+    i64 => i64_be, i64_le;
+    i128 => i128_be, i128_le;
+    f32 => f32_be, f32_le;
+    f64 => f64_be, f64_le;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Uleb128;
+
+impl Processor<u8> for Uleb128 {
+    type Output = u64;
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = u8>,
+    {
+        let fallback = given.snapshot();
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let Some(byte) = given.next() else {
+                return ran_out(given, fallback, Needed::Size(1));
+            };
+            let bits = u64::from(byte & 0x7f);
+            if shift >= 64 || (shift == 63 && bits > 1) {
+                return err(BinaryError::VarintOverflow);
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return done(value, given);
+            }
+            shift += 7;
+        }
+    }
+}
+
+impl Describe for Uleb128 {
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        Expr::special("unsigned LEB128")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Sleb128;
+
+impl Processor<u8> for Sleb128 {
+    type Output = i64;
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = u8>,
+    {
+        let fallback = given.snapshot();
+        let mut value = 0i64;
+        let mut shift = 0;
+        loop {
+            let Some(byte) = given.next() else {
+                return ran_out(given, fallback, Needed::Size(1));
+            };
+            let bits = byte & 0x7f;
+            // Past the last bit only the sign may be repeated
+            if shift >= 64 || (shift == 63 && bits != 0 && bits != 0x7f) {
+                return err(BinaryError::VarintOverflow);
+            }
+            value |= i64::from(bits) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    value |= !0 << shift;
+                }
+                return done(value, given);
+            }
+        }
+    }
+}
+
+impl Describe for Sleb128 {
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        Expr::special("signed LEB128")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ZigZag;
+
+impl Processor<u8> for ZigZag {
+    type Output = i64;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = u8>,
+    {
+        let status = Uleb128.process(given)?;
+        Ok(status.map(|x| (x >> 1) as i64 ^ -((x & 1) as i64)))
+    }
+}
+
+impl Describe for ZigZag {
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        Expr::special("zigzag varint")
+    }
+}
+
+pub fn uleb128() -> Uleb128 {
+    Uleb128
+}
+
+pub fn sleb128() -> Sleb128 {
+    Sleb128
+}
+
+// Protocol buffers share the wire format of unsigned LEB128
+pub fn varint() -> Uleb128 {
+    Uleb128
+}
+
+pub fn zigzag_varint() -> ZigZag {
+    ZigZag
+}
+
+// Outputs can't borrow from the source they were processed from, so the bytes are copied out
+// of the slice the source lends, without going item by item. Borrowed::take_slice borrows them
+// for as long as the input lives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TakeBytes(usize);
+
+impl Processor<u8> for TakeBytes {
+    type Output = Box<[u8]>;
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = u8>,
+    {
+        if let Some(bytes) = given.take_slice(self.0).map(Box::from) {
+            return done(bytes, given);
+        }
+        let fallback = given.snapshot();
+        let mut bytes = Vec::with_capacity(self.0);
+        for idx in 0..self.0 {
+            match given.next() {
+                Some(byte) => bytes.push(byte),
+                None => return ran_out(given, fallback, Needed::Size(self.0 - idx)),
+            }
+        }
+        done(bytes.into_boxed_slice(), given)
+    }
+}
+
+impl Describe for TakeBytes {
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        Expr::Any.repeat(self.0, Some(self.0))
+    }
+}
+
+pub fn take_bytes(amount: usize) -> TakeBytes {
+    TakeBytes(amount)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LengthPrefixed<L, B>(L, B);
+
+impl<L, B> Processor<u8> for LengthPrefixed<L, B>
+where
+    L: Processor<u8>,
+    L::Output: TryInto<usize>,
+    B: Processor<u8>,
+{
+    type Output = B::Output;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = u8>,
+    {
+        guarded("length_prefixed", given, |given| {
+            let fallback = given.snapshot();
+            let (length, rest) = try_done!(self.0.process(given));
+            let Ok(length) = length.try_into() else {
+                return err(BinaryError::LengthOutOfRange);
+            };
+            match self.1.process(Limit::new(rest, length))? {
+                Status::Done(output, rest) => {
+                    let left = rest.remaining();
+                    let mut rest = rest.into_inner();
+                    if left == 0 {
+                        done(output, rest)
+                    } else if rest.peek().is_none() {
+                        ran_out(rest, fallback, Needed::Size(left))
+                    } else {
+                        err(BinaryError::LengthMismatch {
+                            expected: length,
+                            left,
+                        })
+                    }
+                }
+                Status::Mismatch(rest) => rewind(rest.into_inner(), fallback),
+                Status::Incomplete(needed) => Ok(Status::Incomplete(needed)),
+            }
+        })
+    }
+}
+
+// The body is limited to as many bytes as the length tells, which can't be written down
+impl<L, B> Describe for LengthPrefixed<L, B>
+where
+    L: Describe,
+    B: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        let length = self.0.describe(grammar);
+        length.then(self.1.describe(grammar))
+    }
+}
+
+pub fn length_prefixed<L, B>(length: L, body: B) -> LengthPrefixed<L, B>
+where
+    L: Processor<u8>,
+    L::Output: TryInto<usize>,
+    B: Processor<u8>,
+{
+    LengthPrefixed(length, body)
+}
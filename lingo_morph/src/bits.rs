@@ -0,0 +1,275 @@
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    error::Error,
+    fmt::{self, Display},
+};
+
+use crate::{
+    context::Context,
+    done, err,
+    grammar::{Describe, Expr, Grammar},
+    processed,
+    processed::{ran_out, unmatched, Needed},
+    source::{Position, Source},
+    Processed, Processor,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BitOrder {
+    #[default]
+    MsbFirst,
+    LsbFirst,
+}
+
+pub struct BitSource<S> {
+    source: S,
+    order: BitOrder,
+    current: Option<u8>,
+    bit: u8,
+    offset: usize,
+    peeked: bool,
+    // Bits are read out of a copy of the byte, so the ones changed through peek_mut are kept aside
+    edits: BTreeMap<usize, bool>,
+}
+
+impl<S> BitSource<S> {
+    pub fn new(source: S) -> Self {
+        Self::with_order(source, BitOrder::MsbFirst)
+    }
+
+    pub fn with_order(source: S, order: BitOrder) -> Self {
+        Self {
+            source,
+            order,
+            current: None,
+            bit: 0,
+            offset: 0,
+            peeked: false,
+            edits: BTreeMap::new(),
+        }
+    }
+
+    #[inline]
+    pub fn bit_offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S> BitSource<S>
+where
+    S: Source<Item = u8>,
+{
+    fn load(&mut self) -> Option<bool> {
+        let byte = match self.current {
+            Some(byte) if self.bit < 8 => byte,
+            _ => {
+                let byte = self.source.next()?;
+                self.current = Some(byte);
+                self.bit = 0;
+                byte
+            }
+        };
+        let shift = match self.order {
+            BitOrder::MsbFirst => 7 - self.bit,
+            BitOrder::LsbFirst => self.bit,
+        };
+        let bit = byte >> shift & 1 == 1;
+        Some(self.edits.get(&self.offset).copied().unwrap_or(bit))
+    }
+}
+
+#[derive(Debug)]
+pub struct BitSnapshot<T> {
+    inner: T,
+    current: Option<u8>,
+    bit: u8,
+    offset: usize,
+}
+
+impl<S> Source for BitSource<S>
+where
+    S: Source<Item = u8>,
+{
+    type Item = bool;
+    type Snapshot = BitSnapshot<S::Snapshot>;
+    type RollBackErr = S::RollBackErr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.load()?;
+        self.bit += 1;
+        self.offset += 1;
+        Some(next)
+    }
+
+    fn snapshot(&self) -> Self::Snapshot {
+        BitSnapshot {
+            inner: self.source.snapshot(),
+            current: self.current,
+            bit: self.bit,
+            offset: self.offset,
+        }
+    }
+
+    fn roll_back(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        self.source.roll_back(to.inner)?;
+        self.current = to.current;
+        self.bit = to.bit;
+        self.offset = to.offset;
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Option<&Self::Item> {
+        self.peeked = self.load()?;
+        Some(&self.peeked)
+    }
+
+    // Changes stay when rolling back, as they do for data which is owned
+    fn peek_mut(&mut self) -> Option<&mut Self::Item> {
+        let bit = self.load()?;
+        Some(self.edits.entry(self.offset).or_insert(bit))
+    }
+
+    #[inline]
+    fn context(&mut self) -> Option<&mut Context> {
+        self.source.context()
+    }
+
+    #[inline]
+    fn state(&mut self) -> Option<&mut dyn Any> {
+        self.source.state()
+    }
+
+    // The offset is counted in bits so processors can find byte boundaries
+    #[inline]
+    fn position(&self) -> Option<Position> {
+        Some(Position {
+            offset: self.offset,
+            line: 1,
+            column: self.offset + 1,
+        })
+    }
+
+    #[inline]
+    fn is_partial(&self) -> bool {
+        self.source.is_partial()
+    }
+
+    #[inline]
+    fn take_error(&mut self) -> Option<processed::Error> {
+        self.source.take_error()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownBitOffset;
+
+impl Display for UnknownBitOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the source does not report its bit offset")
+    }
+}
+
+impl Error for UnknownBitOffset {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Bits(u32);
+
+impl Processor<bool> for Bits {
+    type Output = u64;
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = bool>,
+    {
+        let fallback = given.snapshot();
+        let mut value = 0;
+        for idx in 0..self.0 {
+            match given.next() {
+                Some(bit) => value = value << 1 | u64::from(bit),
+                None => return ran_out(given, fallback, Needed::Size((self.0 - idx) as usize)),
+            }
+        }
+        done(value, given)
+    }
+}
+
+impl Describe for Bits {
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        Expr::Any.repeat(self.0 as usize, Some(self.0 as usize))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Bit;
+
+impl Processor<bool> for Bit {
+    type Output = bool;
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = bool>,
+    {
+        match given.next() {
+            Some(bit) => done(bit, given),
+            None => unmatched(given),
+        }
+    }
+}
+
+impl Describe for Bit {
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        Expr::Any
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AlignToByte;
+
+impl Processor<bool> for AlignToByte {
+    type Output = ();
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = bool>,
+    {
+        let offset = match given.position() {
+            Some(position) => position.offset,
+            None => return err(UnknownBitOffset),
+        };
+        let fallback = given.snapshot();
+        let padding = (8 - offset % 8) % 8;
+        for idx in 0..padding {
+            if given.next().is_none() {
+                return ran_out(given, fallback, Needed::Size(padding - idx));
+            }
+        }
+        done((), given)
+    }
+}
+
+impl Describe for AlignToByte {
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        Expr::special("padding up to a byte boundary")
+    }
+}
+
+pub fn bits(amount: u32) -> Option<Bits> {
+    if amount <= u64::BITS {
+        Some(Bits(amount))
+    } else {
+        None
+    }
+}
+
+pub fn bool_bit() -> Bit {
+    Bit
+}
+
+pub fn align_to_byte() -> AlignToByte {
+    AlignToByte
+}
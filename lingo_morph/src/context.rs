@@ -1,50 +1,1190 @@
-use std::fmt::Debug;
+use std::{
+    any::{self, Any},
+    cell::RefCell,
+    error::Error,
+    fmt::{self, Debug, Display},
+    io,
+    rc::{Rc, Weak},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
-use crate::{source::Source, Processor, Status, processed};
+#[cfg(feature = "profiling")]
+use crate::profile::Profiler;
+use crate::{
+    cst::{self, Event, SyntaxKind, SyntaxNode},
+    indent::{Indent, Indents},
+    morph::{self, Replacement},
+    processed::{self, Needed, Processed},
+    search::{FindIter, Matches},
+    source::{Borrowed, Partial, Position, Source},
+    source_map::SourceMap,
+    trace::{self, Outcome, Tracer},
+    trivia::Trivia,
+    Processor, Status,
+};
 
-#[derive(Debug)]
-pub enum ProcessingFailed {
+// Drivers which can be cancelled hand back what they processed, as it was before, so processing
+// can go on from there later
+pub enum ProcessingFailed<C = ()> {
     DuringProcessing(processed::Error),
     NoReturn,
+    Incomplete(Needed),
+    LimitExceeded(LimitExceeded),
+    Cancelled(C),
+}
+
+impl<C> ProcessingFailed<C> {
+    // None for a cancellation, which has to be handed what was processed
+    fn from_error(error: processed::Error) -> Option<Self> {
+        if error.is::<Cancelled>() {
+            return None;
+        }
+        Some(match error.downcast::<LimitExceeded>() {
+            Ok(exceeded) => ProcessingFailed::LimitExceeded(*exceeded),
+            Err(error) => ProcessingFailed::DuringProcessing(error),
+        })
+    }
+
+    pub fn map_cancelled<D, F>(self, func: F) -> ProcessingFailed<D>
+    where
+        F: FnOnce(C) -> D,
+    {
+        match self {
+            ProcessingFailed::DuringProcessing(error) => ProcessingFailed::DuringProcessing(error),
+            ProcessingFailed::NoReturn => ProcessingFailed::NoReturn,
+            ProcessingFailed::Incomplete(needed) => ProcessingFailed::Incomplete(needed),
+            ProcessingFailed::LimitExceeded(exceeded) => ProcessingFailed::LimitExceeded(exceeded),
+            ProcessingFailed::Cancelled(cancelled) => ProcessingFailed::Cancelled(func(cancelled)),
+        }
+    }
 }
 
 impl From<processed::Error> for ProcessingFailed {
     fn from(value: processed::Error) -> Self {
-        ProcessingFailed::DuringProcessing(value)
+        Self::from_error(value).unwrap_or(ProcessingFailed::Cancelled(()))
+    }
+}
+
+// Whatever was handed back is left out, sources don't have to be Debug
+impl<C> Debug for ProcessingFailed<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuringProcessing(error) => {
+                f.debug_tuple("DuringProcessing").field(error).finish()
+            }
+            Self::NoReturn => write!(f, "NoReturn"),
+            Self::Incomplete(needed) => f.debug_tuple("Incomplete").field(needed).finish(),
+            Self::LimitExceeded(exceeded) => {
+                f.debug_tuple("LimitExceeded").field(exceeded).finish()
+            }
+            Self::Cancelled(_) => write!(f, "Cancelled"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    Steps(u64),
+    Rollbacks(u64),
+    Depth(usize),
+    Deadline,
+}
+
+impl Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitExceeded::Steps(max) => write!(f, "more than {max} processing steps"),
+            LimitExceeded::Rollbacks(max) => write!(f, "more than {max} rollbacks"),
+            LimitExceeded::Depth(max) => write!(f, "nested deeper than {max} processors"),
+            LimitExceeded::Deadline => write!(f, "processing ran past its deadline"),
+        }
+    }
+}
+
+impl Error for LimitExceeded {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "processing was cancelled")
+    }
+}
+
+impl Error for Cancelled {}
+
+// Clones share the flag, so one can be handed to another thread to cancel from there
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// Only the clock is costly to check, so it's looked at every this many steps
+const DEADLINE_INTERVAL: u64 = 64;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    steps: Option<u64>,
+    rollbacks: Option<u64>,
+    depth: Option<usize>,
+    deadline: Option<Instant>,
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A step is one combinator invocation or repetition, however much input it consumes
+    pub fn steps(mut self, max: u64) -> Self {
+        self.steps = Some(max);
+        self
+    }
+
+    pub fn rollbacks(mut self, max: u64) -> Self {
+        self.rollbacks = Some(max);
+        self
+    }
+
+    pub fn depth(mut self, max: usize) -> Self {
+        self.depth = Some(max);
+        self
+    }
+
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.deadline(Instant::now() + timeout)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub steps: u64,
+    pub rollbacks: u64,
+    pub depth: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Note,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Note => write!(f, "note"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    severity: Severity,
+    message: String,
+    offset: usize,
+}
+
+impl Diagnostic {
+    pub fn new<M>(severity: Severity, message: M, offset: usize) -> Self
+    where
+        M: Into<String>,
+    {
+        Self {
+            severity,
+            message: message.into(),
+            offset,
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    // Amount of items consumed from the source when the diagnostic was reported
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}: {}", self.severity, self.offset, self.message)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Context {
+    diagnostics: Vec<Diagnostic>,
+    offset: usize,
+    indents: Indents,
+    trivia: Option<Rc<Trivia>>,
+    events: Vec<Event>,
+    limits: Limits,
+    usage: Usage,
+    cancellation: Option<CancellationToken>,
+    tracer: Option<trace::Shared>,
+}
+
+impl Context {
+    pub fn report<M>(&mut self, severity: Severity, message: M)
+    where
+        M: Into<String>,
+    {
+        self.diagnostics
+            .push(Diagnostic::new(severity, message, self.offset));
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn indent(&self) -> Option<&Indent> {
+        self.indents.last()
+    }
+
+    pub(crate) fn indents(&self) -> &Indents {
+        &self.indents
+    }
+
+    pub fn push_indent(&mut self, indent: Indent) {
+        self.indents.push(indent);
+    }
+
+    pub fn pop_indent(&mut self) -> Option<Indent> {
+        self.indents.pop()
+    }
+
+    pub fn trivia(&self) -> Option<Rc<Trivia>> {
+        self.trivia.clone()
+    }
+
+    pub fn set_trivia(&mut self, trivia: Trivia) {
+        self.trivia = Some(Rc::new(trivia));
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    // Rolling back doesn't give any of it back
+    pub fn usage(&self) -> Usage {
+        self.usage
+    }
+
+    pub fn set_cancellation(&mut self, token: CancellationToken) {
+        self.cancellation = Some(token);
+    }
+
+    // The tracer is shared so it can still be looked at once processing is over
+    pub fn set_tracer<T>(&mut self, tracer: Rc<RefCell<T>>)
+    where
+        T: Tracer + 'static,
+    {
+        self.tracer = Some(tracer);
+    }
+
+    pub(crate) fn check_cancelled(&self) -> Result<(), Cancelled> {
+        match &self.cancellation {
+            Some(token) if token.is_cancelled() => Err(Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    fn enter(&mut self, name: &'static str) -> Result<(), processed::Error> {
+        self.check_cancelled()?;
+        self.usage.steps += 1;
+        self.usage.depth += 1;
+        match self.limits.depth {
+            Some(max) if self.usage.depth > max => Err(LimitExceeded::Depth(max))?,
+            _ => self.check_steps()?,
+        }
+        self.check_rollbacks()?;
+        let offset = self.offset;
+        trace::emit(self.tracer.as_ref(), trace::Event::Enter { name, offset });
+        Ok(())
+    }
+
+    // Rollbacks happen on the way out of a processor, so they're checked there as well
+    fn leave(&mut self, name: &'static str, outcome: Outcome) -> Result<(), LimitExceeded> {
+        self.usage.depth -= 1;
+        let offset = Some(self.offset);
+        let exit = trace::Event::Exit {
+            name,
+            offset,
+            outcome,
+        };
+        trace::emit(self.tracer.as_ref(), exit);
+        self.check_rollbacks()
+    }
+
+    // A repetition counts as a step of its own, its items might not go through a guarded processor
+    fn repeat(&mut self) -> Result<(), processed::Error> {
+        self.check_cancelled()?;
+        self.usage.steps += 1;
+        Ok(self.check_steps()?)
+    }
+
+    fn check_steps(&self) -> Result<(), LimitExceeded> {
+        let (limits, usage) = (&self.limits, &self.usage);
+        match (limits.steps, limits.deadline) {
+            (Some(max), _) if usage.steps > max => Err(LimitExceeded::Steps(max)),
+            (_, Some(deadline))
+                if usage.steps % DEADLINE_INTERVAL == 1 && Instant::now() >= deadline =>
+            {
+                Err(LimitExceeded::Deadline)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_rollbacks(&self) -> Result<(), LimitExceeded> {
+        match self.limits.rollbacks {
+            Some(max) if self.usage.rollbacks > max => Err(LimitExceeded::Rollbacks(max)),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn events_mut(&mut self) -> &mut Vec<Event> {
+        &mut self.events
+    }
+
+    pub(crate) fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+}
+
+pub fn report<S, M>(source: &mut S, severity: Severity, message: M)
+where
+    S: Source,
+    M: Into<String>,
+{
+    if let Some(context) = source.context() {
+        context.report(severity, message);
+    }
+}
+
+#[inline]
+pub(crate) fn offset<S>(source: &mut S) -> usize
+where
+    S: Source,
+{
+    source.context().map_or(0, |context| context.offset())
+}
+
+#[inline]
+pub fn warn<S, M>(source: &mut S, message: M)
+where
+    S: Source,
+    M: Into<String>,
+{
+    report(source, Severity::Warning, message)
+}
+
+#[inline]
+pub fn note<S, M>(source: &mut S, message: M)
+where
+    S: Source,
+    M: Into<String>,
+{
+    report(source, Severity::Note, message)
+}
+
+// Runs a processor as one step of the context's limits and traces it under the name,
+// custom recursive processors should go through it too
+pub fn guarded<S, O, F>(name: &'static str, mut given: S, process: F) -> Processed<O, S>
+where
+    S: Source,
+    F: FnOnce(S) -> Processed<O, S>,
+{
+    let tracer = match given.context() {
+        Some(context) => {
+            context.enter(name)?;
+            context.tracer.clone()
+        }
+        None => None,
+    };
+    // Without a source to leave through, the exit is traced on the side
+    let unwound = |outcome| {
+        let exit = trace::Event::Exit {
+            name,
+            offset: None,
+            outcome,
+        };
+        trace::emit(tracer.as_ref(), exit);
+    };
+    let status = match process(given) {
+        Ok(status) => status,
+        Err(error) => {
+            unwound(Outcome::Failed);
+            return Err(error);
+        }
+    };
+    Ok(match status {
+        Status::Done(output, mut rest) => {
+            leave(&mut rest, name, Outcome::Done)?;
+            Status::Done(output, rest)
+        }
+        Status::Mismatch(mut rest) => {
+            leave(&mut rest, name, Outcome::Mismatch)?;
+            Status::Mismatch(rest)
+        }
+        incomplete => {
+            unwound(Outcome::Incomplete);
+            incomplete
+        }
+    })
+}
+
+fn leave<S>(source: &mut S, name: &'static str, outcome: Outcome) -> Result<(), LimitExceeded>
+where
+    S: Source,
+{
+    match source.context() {
+        Some(context) => context.leave(name, outcome),
+        None => Ok(()),
+    }
+}
+
+// For loops which may go on for long without going through a guarded processor
+pub(crate) fn cancelled<S>(source: &mut S) -> Result<(), Cancelled>
+where
+    S: Source,
+{
+    match source.context() {
+        Some(context) => context.check_cancelled(),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn taken<S>(source: &mut S, alternative: usize)
+where
+    S: Source,
+{
+    if let Some(context) = source.context() {
+        let taken = trace::Event::Taken { alternative };
+        trace::emit(context.tracer.as_ref(), taken);
+    }
+}
+
+pub(crate) fn repeat<S>(source: &mut S) -> Result<(), processed::Error>
+where
+    S: Source,
+{
+    match source.context() {
+        Some(context) => context.repeat(),
+        None => Ok(()),
+    }
+}
+
+pub fn state<St, S>(source: &mut S) -> Option<&mut St>
+where
+    St: Any,
+    S: Source,
+{
+    source.state()?.downcast_mut()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MissingState(&'static str);
+
+impl MissingState {
+    pub fn of<St>() -> Self {
+        Self(any::type_name::<St>())
+    }
+}
+
+impl Display for MissingState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "no state of type {} is available to the processor",
+            self.0
+        )
+    }
+}
+
+impl Error for MissingState {}
+
+// Object safe view on the user state so it can be saved and restored on roll back
+pub trait State: Any {
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn save(&self) -> Rc<dyn Any>;
+
+    fn restore(&mut self, saved: &dyn Any);
+}
+
+impl<T> State for T
+where
+    T: Any + Clone,
+{
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn save(&self) -> Rc<dyn Any> {
+        Rc::new(self.clone())
+    }
+
+    fn restore(&mut self, saved: &dyn Any) {
+        if let Some(saved) = saved.downcast_ref() {
+            self.clone_from(saved);
+        }
+    }
+}
+
+// Filled once the state is handed out after the snapshot, so it holds the state as it was
+// when the snapshot was taken
+type Save = RefCell<Option<Rc<dyn Any>>>;
+
+pub struct Contextual<'s, S> {
+    source: S,
+    context: Context,
+    state: Option<&'s mut dyn State>,
+    // Saving the state costs as much as cloning it, so it's only done when it may change
+    unsaved: RefCell<Vec<Weak<Save>>>,
+}
+
+impl<S> Contextual<'_, S> {
+    pub fn new(source: S) -> Self {
+        Self::with_context(source, Context::default())
+    }
+
+    pub fn with_context(source: S, context: Context) -> Self {
+        Self {
+            source,
+            context,
+            state: None,
+            unsaved: RefCell::default(),
+        }
+    }
+
+    pub fn with_state<St>(self, state: &mut St) -> Contextual<'_, S>
+    where
+        St: State,
+    {
+        Contextual {
+            source: self.source,
+            context: self.context,
+            state: Some(state),
+            unsaved: self.unsaved,
+        }
+    }
+
+    pub fn into_parts(self) -> (S, Context) {
+        (self.source, self.context)
+    }
+
+    pub(crate) fn source_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    // For a source that is a piece of a larger input, so offsets count from the start of that
+    pub(crate) fn starting_at(mut self, offset: usize) -> Self {
+        self.context.offset = offset;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct Checkpoint<T> {
+    inner: T,
+    offset: usize,
+    diagnostics: usize,
+    indents: Indents,
+    // Processors cut short by an error never got to leave
+    depth: usize,
+    events: usize,
+    state: Option<Rc<Save>>,
+}
+
+impl<S> Source for Contextual<'_, S>
+where
+    S: Source,
+{
+    type Item = S::Item;
+    type Snapshot = Checkpoint<S::Snapshot>;
+    type RollBackErr = S::RollBackErr;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.source.next();
+        if next.is_some() {
+            self.context.offset += 1;
+        }
+        next
+    }
+
+    fn snapshot(&self) -> Self::Snapshot {
+        let state = self.state.as_ref().map(|_| {
+            let save = Rc::new(Save::default());
+            let mut unsaved = self.unsaved.borrow_mut();
+            // Snapshots are mostly dropped in the order they were taken
+            while unsaved.last().is_some_and(|last| last.strong_count() == 0) {
+                unsaved.pop();
+            }
+            unsaved.push(Rc::downgrade(&save));
+            save
+        });
+        Checkpoint {
+            inner: self.source.snapshot(),
+            offset: self.context.offset,
+            diagnostics: self.context.diagnostics.len(),
+            indents: self.context.indents.clone(),
+            depth: self.context.usage.depth,
+            events: self.context.events.len(),
+            state,
+        }
+    }
+
+    fn roll_back(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        let rollback = trace::Event::Rollback {
+            from: self.context.offset,
+            to: to.offset,
+        };
+        self.restore(to)?;
+        trace::emit(self.context.tracer.as_ref(), rollback);
+        self.context.usage.rollbacks += 1;
+        Ok(())
+    }
+
+    // Drivers moving on after a processor is done don't count against the limits either
+    fn restore(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        self.source.restore(to.inner)?;
+        self.context.offset = to.offset;
+        self.context.diagnostics.truncate(to.diagnostics);
+        self.context.indents = to.indents;
+        self.context.usage.depth = to.depth;
+        self.context.events.truncate(to.events);
+        // Without a save the state wasn't handed out since the snapshot
+        let saved = to.state.and_then(|save| save.take());
+        if let (Some(state), Some(saved)) = (self.state.as_deref_mut(), saved) {
+            state.restore(&*saved);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn peek(&mut self) -> Option<&Self::Item> {
+        self.source.peek()
+    }
+
+    #[inline]
+    fn peek_mut(&mut self) -> Option<&mut Self::Item> {
+        self.source.peek_mut()
+    }
+
+    #[inline]
+    fn context(&mut self) -> Option<&mut Context> {
+        Some(&mut self.context)
+    }
+
+    #[inline]
+    fn state(&mut self) -> Option<&mut dyn Any> {
+        let state = self.state.as_deref_mut()?;
+        let unsaved = self.unsaved.get_mut();
+        let unsaved: Vec<_> = unsaved
+            .drain(..)
+            .filter_map(|save| save.upgrade())
+            .collect();
+        if !unsaved.is_empty() {
+            let saved = state.save();
+            for save in unsaved {
+                *save.borrow_mut() = Some(saved.clone());
+            }
+        }
+        Some(state.as_any_mut())
+    }
+
+    #[inline]
+    fn position(&self) -> Option<Position> {
+        self.source.position()
+    }
+
+    #[inline]
+    fn is_partial(&self) -> bool {
+        self.source.is_partial()
+    }
+
+    #[inline]
+    fn take_error(&mut self) -> Option<processed::Error> {
+        self.source.take_error()
+    }
+
+    #[inline]
+    fn as_slice(&self) -> Option<&[Self::Item]> {
+        self.source.as_slice()
+    }
+
+    #[inline]
+    fn take_slice(&mut self, amount: usize) -> Option<&[Self::Item]> {
+        let taken = self.source.take_slice(amount)?;
+        self.context.offset += amount;
+        Some(taken)
     }
 }
 
 #[derive(Debug)]
-pub struct With<'a, I, P>(I, &'a mut P);
+pub struct Diagnosed<T> {
+    pub output: T,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug)]
+pub struct With<'a, I, P>(I, &'a mut P, Context);
 
 impl<'a, I, P> With<'a, I, P> {
     pub(crate) fn new(input: I, processor: &'a mut P) -> Self {
-        Self(input, processor)
+        Self(input, processor, Context::default())
+    }
+
+    pub fn trivia(mut self, trivia: Trivia) -> Self {
+        self.2.trivia = Some(Rc::new(trivia));
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.2.limits = limits;
+        self
+    }
+
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.2.cancellation = Some(token);
+        self
+    }
+
+    // For input that is a piece of a larger one, processed inside the given blocks
+    pub(crate) fn indents(mut self, indents: Indents) -> Self {
+        self.2.indents = indents;
+        self
+    }
+
+    pub fn tracer<T>(mut self, tracer: Rc<RefCell<T>>) -> Self
+    where
+        T: Tracer + 'static,
+    {
+        self.2.set_tracer(tracer);
+        self
     }
 }
 
-impl<S, I, P> With<'_, S, P>
+impl<'a, S, I, P> With<'a, S, P>
 where
     P: Processor<I>,
     S: Source<Item = I>,
 {
-    pub fn process(self) -> Result<P::Output, ProcessingFailed> {
-        match self.1.process(self.0)? {
-            Status::Done(output, _) => Ok(output),
-            Status::Mismatch(_) => Err(ProcessingFailed::NoReturn),
-        }
+    pub fn process(self) -> Result<Diagnosed<P::Output>, Interrupted<'static, S>> {
+        process(self.1, Contextual::with_context(self.0, self.2))
     }
 
-    pub fn fold<A, F>(self, init: A, mut func: F) -> Result<A, processed::Error>
+    pub fn process_with_state<St>(
+        self,
+        state: &mut St,
+    ) -> Result<Diagnosed<P::Output>, Interrupted<'_, S>>
     where
+        St: State,
+    {
+        process(
+            self.1,
+            Contextual::with_context(self.0, self.2).with_state(state),
+        )
+    }
+
+    #[cfg(feature = "profiling")]
+    pub fn profile(
+        self,
+    ) -> (
+        Result<Diagnosed<P::Output>, Interrupted<'static, S>>,
+        Profiler,
+    ) {
+        let profiler = Rc::new(RefCell::new(Profiler::new()));
+        let processed = self.tracer(profiler.clone()).process();
+        (processed, profiler.take())
+    }
+
+    pub fn find_iter(self) -> FindIter<'a, 'static, S, P> {
+        FindIter::new(Contextual::with_context(self.0, self.2), self.1, false)
+    }
+
+    pub fn find_overlapping(self) -> FindIter<'a, 'static, S, P> {
+        FindIter::new(Contextual::with_context(self.0, self.2), self.1, true)
+    }
+
+    pub fn matches(self) -> Matches<'a, 'static, S, P> {
+        Matches::new(self.find_iter())
+    }
+
+    pub fn fold<A, F>(self, init: A, func: F) -> Result<Diagnosed<A>, Interrupted<'static, S>>
+    where
+        F: FnMut(A, P::Output) -> A,
+    {
+        fold(self.1, Contextual::with_context(self.0, self.2), init, func)
+    }
+
+    pub fn fold_with_state<St, A, F>(
+        self,
+        state: &mut St,
+        init: A,
+        func: F,
+    ) -> Result<Diagnosed<A>, Interrupted<'_, S>>
+    where
+        St: State,
         F: FnMut(A, P::Output) -> A,
     {
-        let mut state = init;
-        let mut current = self.0;
-        while let Status::Done(output, rest) = self.1.process(current)? {
-            current = rest;
-            state = func(state, output);
+        fold(
+            self.1,
+            Contextual::with_context(self.0, self.2).with_state(state),
+            init,
+            func,
+        )
+    }
+}
+
+impl<S, P> With<'_, S, P>
+where
+    P: Processor<char>,
+    S: Source<Item = char>,
+{
+    pub fn morph<R>(self, mut replacement: R) -> Result<Diagnosed<String>, ProcessingFailed>
+    where
+        R: Replacement<P::Output>,
+    {
+        let mut output = Vec::new();
+        let diagnostics = morph::run(self.1, self.0, self.2, &mut replacement, &mut output, None)?;
+        Ok(Diagnosed {
+            output: String::from_utf8(output).map_err(processed::Error::from)?,
+            diagnostics,
+        })
+    }
+
+    pub fn morph_into<R, W>(
+        self,
+        mut replacement: R,
+        mut writer: W,
+    ) -> Result<Diagnosed<()>, ProcessingFailed>
+    where
+        R: Replacement<P::Output>,
+        W: io::Write,
+    {
+        let diagnostics = morph::run(self.1, self.0, self.2, &mut replacement, &mut writer, None)?;
+        Ok(Diagnosed {
+            output: (),
+            diagnostics,
+        })
+    }
+
+    pub fn syntax_tree(
+        self,
+        root: SyntaxKind,
+    ) -> Result<Diagnosed<(P::Output, SyntaxNode)>, ProcessingFailed> {
+        cst::parse(self.1, Contextual::with_context(self.0, self.2), root)
+    }
+
+    pub fn morph_mapped<R>(
+        self,
+        mut replacement: R,
+    ) -> Result<Diagnosed<(String, SourceMap)>, ProcessingFailed>
+    where
+        R: Replacement<P::Output>,
+    {
+        let mut output = Vec::new();
+        let mut map = SourceMap::new();
+        let diagnostics = morph::run(
+            self.1,
+            self.0,
+            self.2,
+            &mut replacement,
+            &mut output,
+            Some(&mut map),
+        )?;
+        Ok(Diagnosed {
+            output: (
+                String::from_utf8(output).map_err(processed::Error::from)?,
+                map,
+            ),
+            diagnostics,
+        })
+    }
+
+    pub fn morph_into_mapped<R, W>(
+        self,
+        mut replacement: R,
+        mut writer: W,
+    ) -> Result<Diagnosed<SourceMap>, ProcessingFailed>
+    where
+        R: Replacement<P::Output>,
+        W: io::Write,
+    {
+        let mut map = SourceMap::new();
+        let diagnostics = morph::run(
+            self.1,
+            self.0,
+            self.2,
+            &mut replacement,
+            &mut writer,
+            Some(&mut map),
+        )?;
+        Ok(Diagnosed {
+            output: map,
+            diagnostics,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamError {
+    Mismatch { offset: usize },
+    NoProgress { offset: usize },
+}
+
+impl Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mismatch { offset } => write!(f, "the stream did not match at byte {offset}"),
+            Self::NoProgress { offset } => {
+                write!(f, "the processor consumed nothing at byte {offset}")
+            }
         }
-        Ok(state)
+    }
+}
+
+impl Error for StreamError {}
+
+// Runs the processor repeatedly over bytes arriving in chunks, keeping what it could not finish yet.
+// Processors have no state to resume from, so an incomplete output is processed again from its
+// start, though only once as many more bytes arrived as the processor said it needed
+pub struct Streaming<P> {
+    processor: P,
+    buffer: Vec<u8>,
+    // Buffered bytes there have to be before processing again is worth it
+    awaiting: usize,
+    offset: usize,
+    finished: bool,
+    failed: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<P> Streaming<P>
+where
+    P: Processor<u8>,
+{
+    pub fn new(processor: P) -> Self {
+        Self {
+            processor,
+            buffer: Vec::new(),
+            awaiting: 0,
+            offset: 0,
+            finished: false,
+            failed: false,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Outputs<'_, P> {
+        self.buffer.extend_from_slice(chunk);
+        Outputs(self)
+    }
+
+    // No more input follows, whatever is still buffered has to be processed as is
+    pub fn finish(&mut self) -> Outputs<'_, P> {
+        self.finished = true;
+        Outputs(self)
+    }
+
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    // Amount of bytes consumed by completed outputs
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    // Nothing more will come out, either the input is finished and processed or processing failed
+    pub fn is_exhausted(&self) -> bool {
+        self.failed || (self.finished && self.buffer.is_empty())
+    }
+
+    pub(crate) fn step(&mut self) -> Result<Option<P::Output>, processed::Error> {
+        if self.failed || self.buffer.is_empty() {
+            return Ok(None);
+        }
+        if !self.finished && self.buffer.len() < self.awaiting {
+            return Ok(None);
+        }
+        let mut source = Partial::new(Borrowed::from(&self.buffer[..]));
+        if self.finished {
+            source.finish();
+        }
+        let failure = match self.processor.process(Contextual::new(source))? {
+            Status::Done(output, mut rest) => match rest.take_error() {
+                Some(error) => error,
+                None if rest.context.offset == 0 => StreamError::NoProgress {
+                    offset: self.offset,
+                }
+                .into(),
+                None => {
+                    let used = rest.context.offset;
+                    self.diagnostics
+                        .extend(rest.context.diagnostics.into_iter().map(|diagnostic| {
+                            Diagnostic {
+                                offset: diagnostic.offset + self.offset,
+                                ..diagnostic
+                            }
+                        }));
+                    self.buffer.drain(..used);
+                    self.awaiting = 0;
+                    self.offset += used;
+                    return Ok(Some(output));
+                }
+            },
+            Status::Mismatch(mut rest) => rest.take_error().unwrap_or_else(|| {
+                StreamError::Mismatch {
+                    offset: self.offset,
+                }
+                .into()
+            }),
+            Status::Incomplete(needed) if !self.finished => {
+                self.awaiting = self.buffer.len()
+                    + match needed {
+                        Needed::Size(size) => size,
+                        Needed::Unknown => 1,
+                    };
+                return Ok(None);
+            }
+            Status::Incomplete(needed) => needed.into(),
+        };
+        self.failed = true;
+        Err(failure)
+    }
+}
+
+pub struct Outputs<'a, P>(&'a mut Streaming<P>);
+
+impl<P> Iterator for Outputs<'_, P>
+where
+    P: Processor<u8>,
+{
+    type Item = Result<P::Output, processed::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.step().transpose()
+    }
+}
+
+pub type Interrupted<'s, S> = ProcessingFailed<Box<Contextual<'s, S>>>;
+
+// Cancelled processing is rolled back, and the source handed back in its context
+fn interrupted<'s, S>(
+    error: processed::Error,
+    mut given: Contextual<'s, S>,
+    start: Checkpoint<S::Snapshot>,
+) -> Interrupted<'s, S>
+where
+    S: Source,
+{
+    if let Some(failed) = ProcessingFailed::from_error(error) {
+        return failed;
+    }
+    match given.restore(start) {
+        Ok(()) => ProcessingFailed::Cancelled(Box::new(given)),
+        Err(error) => ProcessingFailed::DuringProcessing(error.into()),
+    }
+}
+
+pub(crate) fn process<'s, P, S, I>(
+    processor: &mut P,
+    mut given: Contextual<'s, S>,
+) -> Result<Diagnosed<P::Output>, Interrupted<'s, S>>
+where
+    P: Processor<I>,
+    S: Source<Item = I>,
+{
+    let start = given.snapshot();
+    let output = match processor.process(&mut given) {
+        Ok(Status::Done(output, _)) => Some(output),
+        Ok(Status::Mismatch(_)) => None,
+        Ok(Status::Incomplete(needed)) => return Err(ProcessingFailed::Incomplete(needed)),
+        Err(error) => return Err(interrupted(error, given, start)),
+    };
+    match (given.take_error(), output) {
+        (Some(error), _) => Err(ProcessingFailed::DuringProcessing(error)),
+        (None, Some(output)) => Ok(Diagnosed {
+            output,
+            diagnostics: given.context.diagnostics,
+        }),
+        (None, None) => Err(ProcessingFailed::NoReturn),
+    }
+}
+
+fn fold<'s, P, S, I, A, F>(
+    processor: &mut P,
+    mut given: Contextual<'s, S>,
+    init: A,
+    mut func: F,
+) -> Result<Diagnosed<A>, Interrupted<'s, S>>
+where
+    P: Processor<I>,
+    S: Source<Item = I>,
+    F: FnMut(A, P::Output) -> A,
+{
+    let start = given.snapshot();
+    let mut state = init;
+    loop {
+        if let Err(error) = repeat(&mut given) {
+            return Err(interrupted(error, given, start));
+        }
+        match processor.process(&mut given) {
+            Ok(Status::Done(output, _)) => state = func(state, output),
+            Ok(Status::Mismatch(_)) => break,
+            Ok(Status::Incomplete(needed)) => return Err(ProcessingFailed::Incomplete(needed)),
+            Err(error) => return Err(interrupted(error, given, start)),
+        }
+    }
+    match given.take_error() {
+        Some(error) => Err(ProcessingFailed::DuringProcessing(error)),
+        None => Ok(Diagnosed {
+            output: state,
+            diagnostics: given.context.diagnostics,
+        }),
     }
 }
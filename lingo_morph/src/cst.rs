@@ -0,0 +1,665 @@
+use std::{
+    fmt::{self, Debug, Display},
+    iter, mem,
+    rc::Rc,
+};
+
+use crate::{
+    context::{guarded, offset, Contextual, Diagnosed, ProcessingFailed},
+    done,
+    grammar::{Describe, Expr, Grammar},
+    indent::Indents,
+    mismatch,
+    processed::incomplete,
+    source::{Source, Span},
+    Processed, Processor, Status,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SyntaxKind(pub u16);
+
+impl SyntaxKind {
+    // Consumed input no token was labelled for, such as skipped whitespace and comments
+    pub const TRIVIA: Self = Self(u16::MAX);
+    // Input left over after the root processor finished
+    pub const ERROR: Self = Self(u16::MAX - 1);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Event {
+    Start(SyntaxKind, usize, Indents),
+    Finish(usize),
+    Token(SyntaxKind, Span),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreenToken {
+    kind: SyntaxKind,
+    text: String,
+}
+
+impl GreenToken {
+    pub fn new<T>(kind: SyntaxKind, text: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            kind,
+            text: text.into(),
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    // Length in chars, matching the offsets of a char source
+    pub fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+}
+
+impl Display for GreenToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    len: usize,
+    children: Vec<GreenElement>,
+    // The blocks the node was processed in, so it can be processed again by itself
+    indents: Indents,
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        Self::within(kind, children, Indents::default())
+    }
+
+    fn within(kind: SyntaxKind, children: Vec<GreenElement>, indents: Indents) -> Self {
+        Self {
+            kind,
+            len: children.iter().map(GreenElement::len).sum(),
+            children,
+            indents,
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+
+    pub fn replace_child(&self, index: usize, child: GreenElement) -> GreenNode {
+        let mut children = self.children.clone();
+        children[index] = child;
+        Self::within(self.kind, children, self.indents.clone())
+    }
+
+    pub(crate) fn indents(&self) -> &Indents {
+        &self.indents
+    }
+}
+
+impl Display for GreenNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.children
+            .iter()
+            .try_for_each(|child| write!(f, "{child}"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            Self::Node(node) => node.kind(),
+            Self::Token(token) => token.kind(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Node(node) => node.len(),
+            Self::Token(token) => token.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Display for GreenElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Node(node) => write!(f, "{node}"),
+            Self::Token(token) => write!(f, "{token}"),
+        }
+    }
+}
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    index: usize,
+    offset: usize,
+}
+
+// A position aware view into a green tree, cheap to clone
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> Self {
+        Self(Rc::new(NodeData {
+            green,
+            parent: None,
+            index: 0,
+            offset: 0,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind()
+    }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.0.offset, self.0.offset + self.0.green.len())
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<&SyntaxNode> {
+        self.0.parent.as_ref()
+    }
+
+    // Position among the children of the parent, tokens included
+    pub fn index(&self) -> usize {
+        self.0.index
+    }
+
+    // A new tree where this node is swapped for the given one, every sibling is shared
+    pub fn replace_with(&self, green: Rc<GreenNode>) -> SyntaxNode {
+        let mut green = green;
+        let mut current = self.clone();
+        while let Some(parent) = current.parent().cloned() {
+            let replaced = parent
+                .green()
+                .replace_child(current.index(), GreenElement::Node(green));
+            green = Rc::new(replaced);
+            current = parent;
+        }
+        SyntaxNode::new_root(green)
+    }
+
+    // The deepest node spanning the whole given span
+    pub fn covering(&self, span: Span) -> SyntaxNode {
+        let mut current = self.clone();
+        while let Some(child) = current.children().into_iter().find(|child| {
+            let covered = child.span();
+            covered.start <= span.start && span.end <= covered.end
+        }) {
+            current = child;
+        }
+        current
+    }
+
+    pub fn ancestors(&self) -> impl Iterator<Item = SyntaxNode> {
+        iter::successors(Some(self.clone()), |node| node.parent().cloned())
+    }
+
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        let mut elements = Vec::with_capacity(self.0.green.children().len());
+        for (index, child) in self.0.green.children().iter().enumerate() {
+            elements.push(match child {
+                GreenElement::Node(green) => SyntaxElement::Node(Self(Rc::new(NodeData {
+                    green: green.clone(),
+                    parent: Some(self.clone()),
+                    index,
+                    offset,
+                }))),
+                GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                    green: green.clone(),
+                    parent: self.clone(),
+                    offset,
+                }),
+            });
+            offset += child.len();
+        }
+        elements
+    }
+
+    pub fn children(&self) -> Vec<SyntaxNode> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(SyntaxElement::into_node)
+            .collect()
+    }
+
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(SyntaxElement::into_token)
+            .collect()
+    }
+
+    // Every node below this one in preorder, this one included
+    pub fn descendants(&self) -> Vec<SyntaxNode> {
+        let mut found = Vec::new();
+        let mut pending = vec![self.clone()];
+        while let Some(node) = pending.pop() {
+            pending.extend(node.children().into_iter().rev());
+            found.push(node);
+        }
+        found
+    }
+
+    pub fn child<N>(&self) -> Option<N>
+    where
+        N: AstNode,
+    {
+        self.children().into_iter().find_map(N::cast)
+    }
+
+    pub fn children_of<N>(&self) -> Vec<N>
+    where
+        N: AstNode,
+    {
+        self.children().into_iter().filter_map(N::cast).collect()
+    }
+
+    pub fn token(&self, kind: SyntaxKind) -> Option<SyntaxToken> {
+        self.tokens().into_iter().find(|token| token.kind() == kind)
+    }
+
+    pub fn text(&self) -> String {
+        self.0.green.to_string()
+    }
+}
+
+impl PartialEq for SyntaxNode {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0.green, &other.0.green) && self.0.offset == other.0.offset
+    }
+}
+
+impl Eq for SyntaxNode {}
+
+impl Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}@{}", self.kind(), self.span())
+    }
+}
+
+impl Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.green)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    parent: SyntaxNode,
+    offset: usize,
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind()
+    }
+
+    pub fn text(&self) -> &str {
+        self.green.text()
+    }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.offset, self.offset + self.green.len())
+    }
+
+    pub fn green(&self) -> &Rc<GreenToken> {
+        &self.green
+    }
+
+    pub fn parent(&self) -> &SyntaxNode {
+        &self.parent
+    }
+}
+
+impl Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}@{} {:?}", self.kind(), self.span(), self.text())
+    }
+}
+
+impl Display for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.text())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            Self::Node(node) => node.kind(),
+            Self::Token(token) => token.kind(),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Node(node) => node.span(),
+            Self::Token(token) => token.span(),
+        }
+    }
+
+    pub fn into_node(self) -> Option<SyntaxNode> {
+        match self {
+            Self::Node(node) => Some(node),
+            Self::Token(_) => None,
+        }
+    }
+
+    pub fn into_token(self) -> Option<SyntaxToken> {
+        match self {
+            Self::Node(_) => None,
+            Self::Token(token) => Some(token),
+        }
+    }
+}
+
+// Typed view over an untyped node
+pub trait AstNode: Sized {
+    fn can_cast(kind: SyntaxKind) -> bool;
+
+    fn cast(node: SyntaxNode) -> Option<Self>;
+
+    fn syntax(&self) -> &SyntaxNode;
+}
+
+fn record<S>(source: &mut S, event: Event) -> Option<usize>
+where
+    S: Source,
+{
+    let events = source.context()?.events_mut();
+    events.push(event);
+    Some(events.len() - 1)
+}
+
+fn mark<S>(source: &mut S) -> Option<usize>
+where
+    S: Source,
+{
+    Some(source.context()?.events_mut().len())
+}
+
+fn forget<S>(source: &mut S, mark: Option<usize>)
+where
+    S: Source,
+{
+    if let (Some(context), Some(mark)) = (source.context(), mark) {
+        context.events_mut().truncate(mark);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Node<P> {
+    processor: P,
+    kind: SyntaxKind,
+}
+
+impl<P, I> Processor<I> for Node<P>
+where
+    P: Processor<I>,
+{
+    type Output = P::Output;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = I>,
+    {
+        guarded("node", given, |mut given| {
+            let start = offset(&mut given);
+            let indents = given.context().map(|context| context.indents().clone());
+            let event = Event::Start(self.kind, start, indents.unwrap_or_default());
+            let mark = record(&mut given, event);
+            match self.processor.process(given)? {
+                Status::Done(output, mut rest) => {
+                    let end = offset(&mut rest);
+                    record(&mut rest, Event::Finish(end));
+                    done(output, rest)
+                }
+                Status::Mismatch(mut rest) => {
+                    forget(&mut rest, mark);
+                    mismatch(rest)
+                }
+                Status::Incomplete(needed) => incomplete(needed),
+            }
+        })
+    }
+}
+
+impl<P> Describe for Node<P>
+where
+    P: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        self.processor.describe(grammar)
+    }
+}
+
+// Whatever the inner processor labelled is flattened into one leaf
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Token<P> {
+    processor: P,
+    kind: SyntaxKind,
+}
+
+impl<P, I> Processor<I> for Token<P>
+where
+    P: Processor<I>,
+{
+    type Output = P::Output;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = I>,
+    {
+        guarded("token", given, |mut given| {
+            let start = offset(&mut given);
+            let mark = mark(&mut given);
+            match self.processor.process(given)? {
+                Status::Done(output, mut rest) => {
+                    let end = offset(&mut rest);
+                    forget(&mut rest, mark);
+                    record(&mut rest, Event::Token(self.kind, Span::new(start, end)));
+                    done(output, rest)
+                }
+                Status::Mismatch(mut rest) => {
+                    forget(&mut rest, mark);
+                    mismatch(rest)
+                }
+                Status::Incomplete(needed) => incomplete(needed),
+            }
+        })
+    }
+}
+
+impl<P> Describe for Token<P>
+where
+    P: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        self.processor.describe(grammar)
+    }
+}
+
+pub fn node<P>(processor: P, kind: SyntaxKind) -> Node<P> {
+    Node { processor, kind }
+}
+
+pub fn token<P>(processor: P, kind: SyntaxKind) -> Token<P> {
+    Token { processor, kind }
+}
+
+struct Builder<'t> {
+    text: &'t str,
+    bytes: Vec<usize>,
+    at: usize,
+    stack: Vec<(SyntaxKind, Indents, Vec<GreenElement>)>,
+}
+
+impl<'t> Builder<'t> {
+    fn new(text: &'t str, root: SyntaxKind) -> Self {
+        let bytes = text
+            .char_indices()
+            .map(|(idx, _)| idx)
+            .chain(iter::once(text.len()))
+            .collect();
+        Self {
+            text,
+            bytes,
+            at: 0,
+            stack: vec![(root, Indents::default(), Vec::new())],
+        }
+    }
+
+    fn push(&mut self, kind: SyntaxKind, until: usize) {
+        let text = &self.text[self.bytes[self.at]..self.bytes[until]];
+        let token = GreenElement::Token(Rc::new(GreenToken::new(kind, text)));
+        if let Some((_, _, children)) = self.stack.last_mut() {
+            children.push(token);
+        }
+        self.at = until;
+    }
+
+    // Consumed input between labelled pieces ends up in trivia tokens
+    fn gap(&mut self, until: usize) {
+        if until > self.at {
+            self.push(SyntaxKind::TRIVIA, until);
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(kind, start, indents) => {
+                self.gap(start);
+                self.stack.push((kind, indents, Vec::new()));
+            }
+            Event::Token(kind, span) => {
+                self.gap(span.start);
+                self.push(kind, span.end);
+            }
+            Event::Finish(end) => {
+                self.gap(end);
+                if let Some((kind, indents, children)) = self.stack.pop() {
+                    let node = GreenNode::within(kind, children, indents);
+                    let node = GreenElement::Node(Rc::new(node));
+                    if let Some((_, _, parent)) = self.stack.last_mut() {
+                        parent.push(node);
+                    }
+                }
+            }
+        }
+    }
+
+    fn finish(mut self, consumed: usize) -> GreenNode {
+        self.gap(consumed);
+        let rest = self.bytes.len() - 1;
+        if rest > self.at {
+            self.push(SyntaxKind::ERROR, rest);
+        }
+        let (kind, indents, children) = self.stack.swap_remove(0);
+        GreenNode::within(kind, children, indents)
+    }
+}
+
+pub(crate) fn build(
+    events: Vec<Event>,
+    text: &str,
+    consumed: usize,
+    root: SyntaxKind,
+) -> GreenNode {
+    let mut builder = Builder::new(text, root);
+    events.into_iter().for_each(|event| builder.event(event));
+    builder.finish(consumed)
+}
+
+pub(crate) fn parse<P, S>(
+    processor: &mut P,
+    given: Contextual<'_, S>,
+    root: SyntaxKind,
+) -> Result<Diagnosed<(P::Output, SyntaxNode)>, ProcessingFailed>
+where
+    P: Processor<char>,
+    S: Source<Item = char>,
+{
+    let start = given.snapshot();
+    let (output, mut rest) = match processor.process(given)? {
+        Status::Done(output, rest) => (output, rest),
+        Status::Mismatch(mut rest) => {
+            return Err(match rest.take_error() {
+                Some(error) => ProcessingFailed::DuringProcessing(error),
+                None => ProcessingFailed::NoReturn,
+            })
+        }
+        Status::Incomplete(needed) => return Err(ProcessingFailed::Incomplete(needed)),
+    };
+    if let Some(error) = rest.take_error() {
+        return Err(ProcessingFailed::DuringProcessing(error));
+    }
+    let consumed = offset(&mut rest);
+    let (events, diagnostics) = match rest.context() {
+        Some(context) => (
+            mem::take(context.events_mut()),
+            context.diagnostics().to_vec(),
+        ),
+        None => Default::default(),
+    };
+    // The whole input is read again so the tree covers every char of it
+    rest.restore(start)
+        .map_err(|error| ProcessingFailed::DuringProcessing(error.into()))?;
+    let text = iter::from_fn(|| rest.next()).collect::<String>();
+    let green = build(events, &text, consumed, root);
+    Ok(Diagnosed {
+        output: (output, SyntaxNode::new_root(Rc::new(green))),
+        diagnostics,
+    })
+}
@@ -0,0 +1,368 @@
+use std::{
+    any::Any,
+    error::Error,
+    fmt::{self, Display},
+};
+
+use crate::{
+    context::Context,
+    processed,
+    source::{Position, Source},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Utf8 => write!(f, "UTF-8"),
+            Self::Utf16Le => write!(f, "UTF-16LE"),
+            Self::Utf16Be => write!(f, "UTF-16BE"),
+            Self::Latin1 => write!(f, "ISO-8859-1"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Malformed {
+    #[default]
+    Replace,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeError {
+    encoding: Encoding,
+    offset: usize,
+}
+
+impl DecodeError {
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    // Byte offset where the malformed sequence starts
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "malformed {} sequence at byte {}",
+            self.encoding, self.offset
+        )
+    }
+}
+
+impl Error for DecodeError {}
+
+const MARKS: [&[u8]; 3] = [&[0xef, 0xbb, 0xbf], &[0xff, 0xfe], &[0xfe, 0xff]];
+
+pub struct Decoder<S> {
+    source: S,
+    encoding: Option<Encoding>,
+    malformed: Malformed,
+    started: bool,
+    offset: usize,
+    peeked: Option<char>,
+    failed: bool,
+    error: Option<processed::Error>,
+}
+
+impl<S> Decoder<S>
+where
+    S: Source<Item = u8>,
+{
+    pub fn new(source: S, encoding: Encoding) -> Self {
+        Self::create(source, Some(encoding))
+    }
+
+    // Picks the encoding from the byte order mark, falling back to UTF-8
+    pub fn detect(source: S) -> Self {
+        Self::create(source, None)
+    }
+
+    fn create(source: S, encoding: Option<Encoding>) -> Self {
+        Self {
+            source,
+            encoding,
+            malformed: Malformed::Replace,
+            started: false,
+            offset: 0,
+            peeked: None,
+            failed: false,
+            error: None,
+        }
+    }
+
+    pub fn malformed(mut self, malformed: Malformed) -> Self {
+        self.malformed = malformed;
+        self
+    }
+
+    pub fn encoding(&self) -> Option<Encoding> {
+        self.encoding
+    }
+
+    // Amount of bytes consumed, a peeked character is counted as consumed
+    pub fn byte_offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+
+    fn fail(&mut self, error: processed::Error) {
+        self.failed = true;
+        self.error = Some(error);
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        let byte = self.source.next()?;
+        self.offset += 1;
+        Some(byte)
+    }
+
+    fn byte_if<P>(&mut self, predicate: P) -> Option<u8>
+    where
+        P: FnOnce(&u8) -> bool,
+    {
+        let byte = self.source.next_if(predicate)?;
+        self.offset += 1;
+        Some(byte)
+    }
+
+    fn eat(&mut self, bytes: &[u8]) -> Result<bool, S::RollBackErr> {
+        let fallback = (self.source.snapshot(), self.offset);
+        for expected in bytes {
+            if self.byte_if(|x| x == expected).is_none() {
+                self.source.roll_back(fallback.0)?;
+                self.offset = fallback.1;
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // Whether the bytes so far could still be the start of a byte order mark split across chunks
+    fn awaits_mark(&mut self) -> Result<bool, S::RollBackErr> {
+        if self.encoding.is_some() || !self.source.is_partial() {
+            return Ok(false);
+        }
+        let fallback = self.source.snapshot();
+        let mut seen = Vec::with_capacity(3);
+        while seen.len() < 3 {
+            match self.source.next() {
+                Some(byte) => seen.push(byte),
+                None => break,
+            }
+        }
+        self.source.restore(fallback)?;
+        Ok(MARKS
+            .iter()
+            .any(|mark| mark.len() > seen.len() && mark.starts_with(&seen)))
+    }
+
+    fn start(&mut self) -> Result<Option<Encoding>, S::RollBackErr> {
+        if self.started {
+            return Ok(Some(self.encoding.unwrap_or(Encoding::Utf8)));
+        }
+        if self.awaits_mark()? {
+            return Ok(None);
+        }
+        self.started = true;
+        let declared = self.encoding;
+        let encoding = match declared {
+            Some(Encoding::Utf8) | None if self.eat(MARKS[0])? => Encoding::Utf8,
+            Some(Encoding::Utf16Le) | None if self.eat(MARKS[1])? => Encoding::Utf16Le,
+            Some(Encoding::Utf16Be) | None if self.eat(MARKS[2])? => Encoding::Utf16Be,
+            Some(encoding) => encoding,
+            None => Encoding::Utf8,
+        };
+        self.encoding = Some(encoding);
+        Ok(Some(encoding))
+    }
+
+    fn decode(&mut self) -> Option<char> {
+        if self.failed {
+            return None;
+        }
+        let encoding = match self.start() {
+            Ok(Some(encoding)) => encoding,
+            Ok(None) => return None,
+            Err(error) => {
+                self.fail(error.into());
+                return None;
+            }
+        };
+        let start = self.offset;
+        let fallback = self.source.snapshot();
+        let decoded = match encoding {
+            Encoding::Utf8 => self.utf8(),
+            Encoding::Utf16Le => self.utf16(u16::from_le_bytes),
+            Encoding::Utf16Be => self.utf16(u16::from_be_bytes),
+            Encoding::Latin1 => self.byte().map(|byte| Some(char::from(byte))),
+        };
+        let decoded = match decoded {
+            Some(decoded) => decoded,
+            None if self.offset == start => return None,
+            // The rest of a sequence cut short may still arrive, until then the input ends before it
+            None if self.source.is_partial() => {
+                if let Err(error) = self.source.roll_back(fallback) {
+                    self.fail(error.into());
+                }
+                self.offset = start;
+                return None;
+            }
+            None => None,
+        };
+        match (decoded, self.malformed) {
+            (Some(next), _) => Some(next),
+            (None, Malformed::Replace) => Some(char::REPLACEMENT_CHARACTER),
+            (None, Malformed::Error) => {
+                self.fail(
+                    DecodeError {
+                        encoding,
+                        offset: start,
+                    }
+                    .into(),
+                );
+                None
+            }
+        }
+    }
+
+    // The outer option signals the input ran out, the inner one a malformed sequence
+    fn utf8(&mut self) -> Option<Option<char>> {
+        let lead = self.byte()?;
+        let (length, low, high) = match lead {
+            0x00..=0x7f => return Some(Some(char::from(lead))),
+            0xc2..=0xdf => (1, 0x80, 0xbf),
+            0xe0 => (2, 0xa0, 0xbf),
+            0xe1..=0xec | 0xee..=0xef => (2, 0x80, 0xbf),
+            0xed => (2, 0x80, 0x9f),
+            0xf0 => (3, 0x90, 0xbf),
+            0xf1..=0xf3 => (3, 0x80, 0xbf),
+            0xf4 => (3, 0x80, 0x8f),
+            _ => return Some(None),
+        };
+        let mut value = u32::from(lead) & (0x7f >> (length + 1));
+        for idx in 0..length {
+            let (low, high) = if idx == 0 { (low, high) } else { (0x80, 0xbf) };
+            match self.byte_if(|x| (low..=high).contains(x)) {
+                Some(byte) => value = value << 6 | u32::from(byte & 0x3f),
+                None if self.source.peek().is_none() => return None,
+                None => return Some(None),
+            }
+        }
+        Some(char::from_u32(value))
+    }
+
+    fn utf16(&mut self, unit: fn([u8; 2]) -> u16) -> Option<Option<char>> {
+        let first = self.byte()?;
+        let second = self.byte()?;
+        let high = unit([first, second]);
+        if !(0xd800..=0xdbff).contains(&high) {
+            return Some(char::from_u32(u32::from(high)));
+        }
+        let fallback = (self.source.snapshot(), self.offset);
+        let low = unit([self.byte()?, self.byte()?]);
+        if (0xdc00..=0xdfff).contains(&low) {
+            let value = 0x10000 + ((u32::from(high) - 0xd800) << 10) + (u32::from(low) - 0xdc00);
+            Some(char::from_u32(value))
+        } else {
+            if let Err(error) = self.source.roll_back(fallback.0) {
+                self.fail(error.into());
+            }
+            self.offset = fallback.1;
+            Some(None)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DecoderSnapshot<T> {
+    inner: T,
+    encoding: Option<Encoding>,
+    started: bool,
+    offset: usize,
+    peeked: Option<char>,
+}
+
+impl<S> Source for Decoder<S>
+where
+    S: Source<Item = u8>,
+{
+    type Item = char;
+    type Snapshot = DecoderSnapshot<S::Snapshot>;
+    type RollBackErr = S::RollBackErr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.peeked.take().or_else(|| self.decode())
+    }
+
+    fn snapshot(&self) -> Self::Snapshot {
+        DecoderSnapshot {
+            inner: self.source.snapshot(),
+            encoding: self.encoding,
+            started: self.started,
+            offset: self.offset,
+            peeked: self.peeked,
+        }
+    }
+
+    fn roll_back(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        self.source.roll_back(to.inner)?;
+        self.encoding = to.encoding;
+        self.started = to.started;
+        self.offset = to.offset;
+        self.peeked = to.peeked;
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Option<&Self::Item> {
+        self.peek_mut().map(|x| &*x)
+    }
+
+    fn peek_mut(&mut self) -> Option<&mut Self::Item> {
+        if self.peeked.is_none() {
+            self.peeked = self.decode();
+        }
+        self.peeked.as_mut()
+    }
+
+    #[inline]
+    fn context(&mut self) -> Option<&mut Context> {
+        self.source.context()
+    }
+
+    #[inline]
+    fn state(&mut self) -> Option<&mut dyn Any> {
+        self.source.state()
+    }
+
+    // Where the inner source is, which is past a peeked character
+    #[inline]
+    fn position(&self) -> Option<Position> {
+        self.source.position()
+    }
+
+    #[inline]
+    fn is_partial(&self) -> bool {
+        self.source.is_partial()
+    }
+
+    fn take_error(&mut self) -> Option<processed::Error> {
+        self.error.take().or_else(|| self.source.take_error())
+    }
+}
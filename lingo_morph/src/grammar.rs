@@ -0,0 +1,462 @@
+use std::{
+    fmt::Write,
+    ops::{Bound, RangeBounds},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Empty,
+    Any,
+    Char(char),
+    Byte(u8),
+    // Both ends are included
+    Range(Box<Expr>, Box<Expr>),
+    Sequence(Vec<Expr>),
+    Choice(Vec<Expr>),
+    Repeat {
+        expr: Box<Expr>,
+        min: usize,
+        max: Option<usize>,
+    },
+    // Whatever the first matches but the second doesn't
+    Except(Box<Expr>, Box<Expr>),
+    Rule(&'static str),
+    // What can only be told in words
+    Special(String),
+}
+
+impl Expr {
+    pub fn special<T>(text: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Expr::Special(text.into())
+    }
+
+    pub fn repeat(self, min: usize, max: Option<usize>) -> Self {
+        Expr::Repeat {
+            expr: Box::new(self),
+            min,
+            max,
+        }
+    }
+
+    // Nested sequences are flattened and empty parts dropped
+    pub fn then(self, next: Expr) -> Self {
+        let mut parts = match self {
+            Expr::Sequence(parts) => parts,
+            Expr::Empty => Vec::new(),
+            expr => vec![expr],
+        };
+        match next {
+            Expr::Sequence(next) => parts.extend(next),
+            Expr::Empty => {}
+            next => parts.push(next),
+        }
+        match parts.len() {
+            0 => Expr::Empty,
+            1 => parts.swap_remove(0),
+            _ => Expr::Sequence(parts),
+        }
+    }
+
+    pub fn or(self, other: Expr) -> Self {
+        let mut options = match self {
+            Expr::Choice(options) => options,
+            expr => vec![expr],
+        };
+        match other {
+            Expr::Choice(other) => options.extend(other),
+            other => options.push(other),
+        }
+        Expr::Choice(options)
+    }
+}
+
+// Symbols a source can be made of and which can be written down as terminals
+pub trait Symbol: Sized {
+    const FIRST: Self;
+    const LAST: Self;
+
+    fn expr(&self) -> Expr;
+
+    fn before(&self) -> Option<Self>;
+
+    fn after(&self) -> Option<Self>;
+
+    fn range<R>(range: &R) -> Expr
+    where
+        R: RangeBounds<Self>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(start) => start.expr(),
+            Bound::Excluded(start) => start.after().map_or(Expr::Empty, |x| x.expr()),
+            Bound::Unbounded => Self::FIRST.expr(),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => end.expr(),
+            Bound::Excluded(end) => end.before().map_or(Expr::Empty, |x| x.expr()),
+            Bound::Unbounded => Self::LAST.expr(),
+        };
+        match (start, end) {
+            (Expr::Empty, _) | (_, Expr::Empty) => Expr::special("nothing"),
+            (start, end) => Expr::Range(Box::new(start), Box::new(end)),
+        }
+    }
+}
+
+impl Symbol for char {
+    const FIRST: Self = '\0';
+    const LAST: Self = char::MAX;
+
+    fn expr(&self) -> Expr {
+        Expr::Char(*self)
+    }
+
+    fn before(&self) -> Option<Self> {
+        match *self {
+            '\u{e000}' => Some('\u{d7ff}'),
+            next => char::from_u32((next as u32).checked_sub(1)?),
+        }
+    }
+
+    fn after(&self) -> Option<Self> {
+        match *self {
+            '\u{d7ff}' => Some('\u{e000}'),
+            next => char::from_u32(next as u32 + 1),
+        }
+    }
+}
+
+impl Symbol for u8 {
+    const FIRST: Self = u8::MIN;
+    const LAST: Self = u8::MAX;
+
+    fn expr(&self) -> Expr {
+        Expr::Byte(*self)
+    }
+
+    fn before(&self) -> Option<Self> {
+        self.checked_sub(1)
+    }
+
+    fn after(&self) -> Option<Self> {
+        self.checked_add(1)
+    }
+}
+
+pub trait Describe {
+    fn describe(&self, grammar: &mut Grammar) -> Expr;
+
+    // How it reads when folded, which is any number of times unless it bounds that itself
+    fn describe_repeated(&self, grammar: &mut Grammar) -> Expr {
+        self.describe(grammar).repeat(0, None)
+    }
+}
+
+impl<D> Describe for &mut D
+where
+    D: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        (**self).describe(grammar)
+    }
+
+    fn describe_repeated(&self, grammar: &mut Grammar) -> Expr {
+        (**self).describe_repeated(grammar)
+    }
+}
+
+// The name of the rule a processor is described by when it wasn't named
+const START: &str = "start";
+
+// Rules are kept in the order they're first referred to, so the grammar reads top down
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Grammar {
+    rules: Vec<(&'static str, Expr)>,
+}
+
+impl Grammar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn of<D>(processor: &D) -> Self
+    where
+        D: Describe,
+    {
+        let mut grammar = Self::new();
+        let start = processor.describe(&mut grammar);
+        if !matches!(start, Expr::Rule(_)) {
+            grammar.rules.insert(0, (START, start));
+        }
+        grammar
+    }
+
+    pub fn rules(&self) -> &[(&'static str, Expr)] {
+        &self.rules
+    }
+
+    pub fn rule(&self, name: &str) -> Option<&Expr> {
+        let (_, expr) = self.rules.iter().find(|(rule, _)| *rule == name)?;
+        Some(expr)
+    }
+
+    // Defining a rule before describing what's in it stops recursive processors from describing forever
+    pub fn define<D>(&mut self, name: &'static str, processor: &D) -> Expr
+    where
+        D: Describe,
+    {
+        if self.rule(name).is_none() {
+            self.rules.push((name, Expr::Empty));
+            let idx = self.rules.len() - 1;
+            self.rules[idx].1 = processor.describe(self);
+        }
+        Expr::Rule(name)
+    }
+
+    // ISO 14977 notation, with special sequences for what it can't express
+    pub fn to_ebnf(&self) -> String {
+        let mut ebnf = String::new();
+        for (name, expr) in &self.rules {
+            let _ = write!(ebnf, "{name} = ");
+            write_ebnf(&mut ebnf, expr, 0);
+            ebnf.push_str(" ;\n");
+        }
+        ebnf
+    }
+
+    // RFC 5234 notation, where string literals ignore case so letters are written as values
+    pub fn to_abnf(&self) -> String {
+        let mut abnf = String::new();
+        for (name, expr) in &self.rules {
+            let _ = write!(abnf, "{} = ", abnf_name(name));
+            write_abnf(&mut abnf, expr, 0);
+            abnf.push('\n');
+        }
+        abnf
+    }
+}
+
+// Binding strength, an expression binding looser than where it's written goes in parentheses
+const CHOICE: u8 = 0;
+const SEQUENCE: u8 = 1;
+const EXCEPT: u8 = 2;
+const FACTOR: u8 = 3;
+const PRIMARY: u8 = 4;
+
+// Only optional and any number of times have a notation, counts are made up from those
+fn ebnf_repeat(min: usize, max: Option<usize>) -> Vec<(String, &'static str, u8)> {
+    let mut parts = Vec::new();
+    match min {
+        0 => {}
+        1 => parts.push((String::new(), "", EXCEPT)),
+        min => parts.push((format!("{min} * "), "", PRIMARY)),
+    }
+    match max.map(|max| max.saturating_sub(min)) {
+        Some(0) => {}
+        Some(1) => parts.push(("[ ".into(), " ]", CHOICE)),
+        Some(rest) => parts.push((format!("{rest} * [ "), " ]", CHOICE)),
+        None => parts.push(("{ ".into(), " }", CHOICE)),
+    }
+    parts
+}
+
+fn ebnf_binding(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Choice(_) => CHOICE,
+        Expr::Sequence(_) => SEQUENCE,
+        Expr::Except(..) => EXCEPT,
+        Expr::Repeat { min, max, .. } => match ebnf_repeat(*min, *max).as_slice() {
+            [(prefix, _, _)] if prefix.ends_with("* ") || prefix.ends_with("* [ ") => FACTOR,
+            [(prefix, _, _)] if !prefix.is_empty() => PRIMARY,
+            _ => SEQUENCE,
+        },
+        _ => PRIMARY,
+    }
+}
+
+fn write_ebnf(into: &mut String, expr: &Expr, outer: u8) {
+    if ebnf_binding(expr) < outer {
+        into.push_str("( ");
+        write_ebnf(into, expr, CHOICE);
+        into.push_str(" )");
+        return;
+    }
+    match expr {
+        Expr::Empty => into.push_str("\"\""),
+        Expr::Any => into.push_str("? any ?"),
+        Expr::Char(next) => ebnf_char(into, *next),
+        Expr::Byte(byte) => {
+            let _ = write!(into, "? byte {byte:#04x} ?");
+        }
+        Expr::Range(start, end) => {
+            into.push_str("? ");
+            write_ebnf(into, start, PRIMARY);
+            into.push_str(" to ");
+            write_ebnf(into, end, PRIMARY);
+            into.push_str(" ?");
+        }
+        Expr::Sequence(parts) => write_joined(into, parts, " , ", EXCEPT, write_ebnf),
+        Expr::Choice(options) => write_joined(into, options, " | ", SEQUENCE, write_ebnf),
+        Expr::Repeat { expr, min, max } => {
+            let parts = ebnf_repeat(*min, *max);
+            if parts.is_empty() {
+                into.push_str("\"\"");
+            }
+            for (idx, (prefix, suffix, operand)) in parts.into_iter().enumerate() {
+                if idx > 0 {
+                    into.push_str(" , ");
+                }
+                into.push_str(&prefix);
+                write_ebnf(into, expr, operand);
+                into.push_str(suffix);
+            }
+        }
+        Expr::Except(expr, except) => {
+            write_ebnf(into, expr, FACTOR);
+            into.push_str(" - ");
+            write_ebnf(into, except, FACTOR);
+        }
+        Expr::Rule(name) => into.push_str(name),
+        Expr::Special(text) => {
+            let _ = write!(into, "? {} ?", text.replace('?', ""));
+        }
+    }
+}
+
+fn ebnf_char(into: &mut String, next: char) {
+    match next {
+        '"' => into.push_str("'\"'"),
+        next if next.is_control() || next.is_whitespace() && next != ' ' => {
+            let _ = write!(into, "? U+{:04X} ?", u32::from(next));
+        }
+        next => {
+            let _ = write!(into, "\"{next}\"");
+        }
+    }
+}
+
+fn write_abnf(into: &mut String, expr: &Expr, outer: u8) {
+    let inner = match expr {
+        Expr::Choice(_) => CHOICE,
+        Expr::Sequence(_) => SEQUENCE,
+        // A repeat of a repeat would read as one with both counts
+        Expr::Repeat {
+            min: 0,
+            max: Some(1),
+            ..
+        } => PRIMARY,
+        Expr::Repeat { .. } => FACTOR,
+        _ => PRIMARY,
+    };
+    if inner < outer {
+        into.push('(');
+        write_abnf(into, expr, CHOICE);
+        into.push(')');
+        return;
+    }
+    match expr {
+        Expr::Empty => into.push_str("\"\""),
+        Expr::Any => into.push_str("<any>"),
+        Expr::Char(next) => abnf_char(into, *next),
+        Expr::Byte(byte) => {
+            let _ = write!(into, "%x{byte:02X}");
+        }
+        Expr::Range(start, end) => match (&**start, &**end) {
+            (Expr::Char(start), Expr::Char(end)) => {
+                let _ = write!(into, "%x{:X}-{:X}", u32::from(*start), u32::from(*end));
+            }
+            (Expr::Byte(start), Expr::Byte(end)) => {
+                let _ = write!(into, "%x{start:02X}-{end:02X}");
+            }
+            _ => into.push_str("<range>"),
+        },
+        Expr::Sequence(parts) => write_joined(into, parts, " ", FACTOR, write_abnf),
+        Expr::Choice(options) => write_joined(into, options, " / ", SEQUENCE, write_abnf),
+        Expr::Repeat {
+            expr,
+            min: 0,
+            max: Some(1),
+        } => {
+            into.push('[');
+            write_abnf(into, expr, CHOICE);
+            into.push(']');
+        }
+        Expr::Repeat { expr, min, max } => {
+            match (*min, *max) {
+                (min, Some(max)) if min == max => {
+                    let _ = write!(into, "{min}");
+                }
+                (0, None) => into.push('*'),
+                (min, None) => {
+                    let _ = write!(into, "{min}*");
+                }
+                (0, Some(max)) => {
+                    let _ = write!(into, "*{max}");
+                }
+                (min, Some(max)) => {
+                    let _ = write!(into, "{min}*{max}");
+                }
+            }
+            write_abnf(into, expr, PRIMARY);
+        }
+        Expr::Except(expr, except) => {
+            // There's no notation for it, so it's told in words
+            let mut text = String::new();
+            write_abnf(&mut text, expr, PRIMARY);
+            text.push_str(" except ");
+            write_abnf(&mut text, except, PRIMARY);
+            let _ = write!(into, "<{}>", text.replace(['<', '>'], ""));
+        }
+        Expr::Rule(name) => into.push_str(&abnf_name(name)),
+        Expr::Special(text) => {
+            let _ = write!(into, "<{}>", text.replace(['<', '>'], ""));
+        }
+    }
+}
+
+fn abnf_char(into: &mut String, next: char) {
+    if next.is_ascii_graphic() && !next.is_ascii_alphabetic() && next != '"' || next == ' ' {
+        let _ = write!(into, "\"{next}\"");
+    } else {
+        let _ = write!(into, "%x{:02X}", u32::from(next));
+    }
+}
+
+// Rule names can only hold letters, digits and hyphens
+fn abnf_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|x| if x.is_ascii_alphanumeric() { x } else { '-' })
+        .collect::<String>();
+    match name.starts_with(|x: char| x.is_ascii_alphabetic()) {
+        true => name,
+        false => format!("r-{name}"),
+    }
+}
+
+fn write_joined(
+    into: &mut String,
+    exprs: &[Expr],
+    separator: &str,
+    outer: u8,
+    write: fn(&mut String, &Expr, u8),
+) {
+    for (idx, expr) in exprs.iter().enumerate() {
+        if idx > 0 {
+            into.push_str(separator);
+        }
+        write(into, expr, outer);
+    }
+}
+
+pub(crate) fn one_of<I>(items: &[I]) -> Expr
+where
+    I: Symbol,
+{
+    let options = items.iter().map(Symbol::expr);
+    options
+        .reduce(Expr::or)
+        .unwrap_or_else(|| Expr::special("nothing"))
+}
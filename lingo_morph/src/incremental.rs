@@ -0,0 +1,230 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    context::{CancellationToken, Diagnosed, Diagnostic, Limits, ProcessingFailed, With},
+    cst::{GreenElement, GreenNode, SyntaxKind, SyntaxNode},
+    source::{BoxedSlice, Located, Position, Span},
+    trivia::Trivia,
+    Processor,
+};
+
+// Offsets count chars, like every char source
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TextEdit {
+    pub range: Span,
+    pub replacement: String,
+}
+
+impl TextEdit {
+    pub fn new<R, T>(range: R, replacement: T) -> Self
+    where
+        R: Into<Span>,
+        T: Into<String>,
+    {
+        Self {
+            range: range.into(),
+            replacement: replacement.into(),
+        }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut chars = text.chars();
+        let mut edited = chars.by_ref().take(self.range.start).collect::<String>();
+        edited.push_str(&self.replacement);
+        edited.extend(chars.skip(self.range.len()));
+        edited
+    }
+
+    fn relative_to(&self, start: usize) -> Self {
+        Self {
+            range: Span::new(self.range.start - start, self.range.end - start),
+            replacement: self.replacement.clone(),
+        }
+    }
+}
+
+type Parsed = Diagnosed<Rc<GreenNode>>;
+type Full<'p> = Box<dyn FnMut(&str, &Settings) -> Result<Parsed, ProcessingFailed> + 'p>;
+type Reparse<'p> = Box<
+    dyn FnMut(&str, &Settings, &GreenNode, Position) -> Result<Option<Parsed>, ProcessingFailed>
+        + 'p,
+>;
+
+#[derive(Default)]
+struct Settings {
+    trivia: Option<Trivia>,
+    limits: Limits,
+    cancellation: Option<CancellationToken>,
+}
+
+// A reparse starts at the position and inside the blocks of its node, but the steps, rollbacks and
+// depth it's limited to count from there, as if the node was all of the input
+pub struct Incremental<'p> {
+    full: Full<'p>,
+    reparsers: HashMap<SyntaxKind, Reparse<'p>>,
+    settings: Settings,
+}
+
+impl<'p> Incremental<'p> {
+    pub fn new<P>(root: SyntaxKind, mut processor: P) -> Self
+    where
+        P: Processor<char> + 'p,
+    {
+        Self {
+            full: Box::new(move |text, settings| {
+                let parsed = configured(&mut processor, text, settings, Position::START)
+                    .syntax_tree(root)?;
+                Ok(Diagnosed {
+                    output: parsed.output.1.green().clone(),
+                    diagnostics: parsed.diagnostics,
+                })
+            }),
+            reparsers: HashMap::new(),
+            settings: Settings::default(),
+        }
+    }
+
+    // The processor has to label its result with the given kind, as the processor used in the full parse does
+    pub fn reparser<P>(mut self, kind: SyntaxKind, mut processor: P) -> Self
+    where
+        P: Processor<char> + 'p,
+    {
+        let reparse = move |text: &str, settings: &Settings, node: &GreenNode, at| {
+            let parsed = match configured(&mut processor, text, settings, at)
+                .indents(node.indents().clone())
+                .syntax_tree(SyntaxKind::ERROR)
+            {
+                Ok(parsed) => parsed,
+                Err(
+                    failed @ (ProcessingFailed::LimitExceeded(_) | ProcessingFailed::Cancelled(_)),
+                ) => return Err(failed),
+                Err(_) => return Ok(None),
+            };
+            // Anything but exactly one node of the kind covering all of the text can't be spliced in
+            match parsed.output.1.green().children() {
+                [GreenElement::Node(node)] if node.kind() == kind => Ok(Some(Diagnosed {
+                    output: node.clone(),
+                    diagnostics: parsed.diagnostics,
+                })),
+                _ => Ok(None),
+            }
+        };
+        self.reparsers.insert(kind, Box::new(reparse));
+        self
+    }
+
+    pub fn trivia(mut self, trivia: Trivia) -> Self {
+        self.settings.trivia = Some(trivia);
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.settings.limits = limits;
+        self
+    }
+
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.settings.cancellation = Some(token);
+        self
+    }
+
+    pub fn parse(&mut self, text: &str) -> Result<Diagnosed<SyntaxNode>, ProcessingFailed> {
+        let parsed = (self.full)(text, &self.settings)?;
+        Ok(Diagnosed {
+            output: SyntaxNode::new_root(parsed.output),
+            diagnostics: parsed.diagnostics,
+        })
+    }
+
+    // Reprocesses the innermost node with a reparser which strictly contains the edit, the whole text otherwise
+    pub fn edit(
+        &mut self,
+        previous: &Diagnosed<SyntaxNode>,
+        edit: &TextEdit,
+    ) -> Result<Diagnosed<SyntaxNode>, ProcessingFailed> {
+        let tree = &previous.output;
+        for candidate in tree.covering(edit.range).ancestors() {
+            let span = candidate.span();
+            if candidate.parent().is_none() {
+                break;
+            }
+            if span.start == edit.range.start || edit.range.end == span.end {
+                continue;
+            }
+            // Diagnostics on the edges can't be told apart from those of the neighbours
+            let diagnostics = &previous.diagnostics;
+            if diagnostics
+                .iter()
+                .any(|x| x.offset() == span.start || x.offset() == span.end)
+            {
+                continue;
+            }
+            let Some(reparse) = self.reparsers.get_mut(&candidate.kind()) else {
+                continue;
+            };
+            let text = edit.relative_to(span.start).apply(&candidate.text());
+            let at = position(tree, span.start);
+            if let Some(reparsed) = reparse(&text, &self.settings, candidate.green(), at)? {
+                let diagnostics = splice(diagnostics, span, &reparsed);
+                return Ok(Diagnosed {
+                    output: candidate.replace_with(reparsed.output),
+                    diagnostics,
+                });
+            }
+        }
+        self.parse(&edit.apply(&tree.text()))
+    }
+}
+
+// Diagnostics are reported in the order of their offsets, so the reparsed ones go in between
+fn splice(diagnostics: &[Diagnostic], span: Span, reparsed: &Parsed) -> Vec<Diagnostic> {
+    let len = reparsed.output.len();
+    let moved = |diagnostic: &Diagnostic, offset| {
+        let message = diagnostic.message().to_owned();
+        Diagnostic::new(diagnostic.severity(), message, offset)
+    };
+    let before = diagnostics.iter().take_while(|x| x.offset() < span.start);
+    let inside = reparsed.diagnostics.iter();
+    let after = diagnostics.iter().filter(|x| x.offset() > span.end);
+    before
+        .cloned()
+        .chain(inside.map(|x| moved(x, x.offset() + span.start)))
+        .chain(after.map(|x| moved(x, x.offset() - span.end + span.start + len)))
+        .collect()
+}
+
+fn position(tree: &SyntaxNode, offset: usize) -> Position {
+    let mut position = Position::START;
+    for next in tree.text().chars().take(offset) {
+        position.offset += 1;
+        if next == '\n' {
+            position.line += 1;
+            position.column = 1;
+        } else {
+            position.column += 1;
+        }
+    }
+    position
+}
+
+fn configured<'a, P>(
+    processor: &'a mut P,
+    text: &str,
+    settings: &Settings,
+    at: Position,
+) -> With<'a, Located<BoxedSlice<char>>, P>
+where
+    P: Processor<char>,
+{
+    let source = BoxedSlice::from(text.chars().collect::<Vec<_>>());
+    let mut with = processor
+        .with(Located::new(source).starting_at(at))
+        .limits(settings.limits);
+    if let Some(trivia) = &settings.trivia {
+        with = with.trivia(trivia.clone());
+    }
+    match &settings.cancellation {
+        Some(token) => with.cancellation(token.clone()),
+        None => with,
+    }
+}
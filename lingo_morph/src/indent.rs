@@ -0,0 +1,381 @@
+use std::{
+    cmp::Ordering,
+    error::Error,
+    fmt::{self, Display},
+    mem,
+    rc::Rc,
+};
+
+use crate::{
+    context::{cancelled, guarded},
+    done, err,
+    grammar::{Describe, Expr, Grammar},
+    mismatch,
+    processed::{incomplete, ran_out, rewind, Needed},
+    source::{Position, Source},
+    Processed, Processor, Status,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Indent(String);
+
+impl Indent {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn width(&self) -> usize {
+        self.0.chars().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // None when neither indent is a prefix of the other, meaning tabs and spaces got mixed up
+    pub fn compare(&self, other: &Indent) -> Option<Ordering> {
+        if self.0 == other.0 {
+            Some(Ordering::Equal)
+        } else if self.0.starts_with(&other.0) {
+            Some(Ordering::Greater)
+        } else if other.0.starts_with(&self.0) {
+            Some(Ordering::Less)
+        } else {
+            None
+        }
+    }
+}
+
+// Snapshots share the levels instead of copying the whole stack
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub(crate) struct Indents(Option<Rc<Level>>);
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct Level {
+    indent: Indent,
+    outer: Indents,
+}
+
+impl Indents {
+    pub(crate) fn last(&self) -> Option<&Indent> {
+        self.0.as_ref().map(|level| &level.indent)
+    }
+
+    pub(crate) fn push(&mut self, indent: Indent) {
+        let outer = mem::take(self);
+        self.0 = Some(Rc::new(Level { indent, outer }));
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<Indent> {
+        let level = self.0.take()?;
+        match Rc::try_unwrap(level) {
+            Ok(level) => {
+                *self = level.outer;
+                Some(level.indent)
+            }
+            Err(shared) => {
+                *self = shared.outer.clone();
+                Some(shared.indent.clone())
+            }
+        }
+    }
+}
+
+impl Display for Indent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tabs = self.0.chars().filter(|x| *x == '\t').count();
+        let spaces = self.0.len() - tabs;
+        write!(f, "{tabs} tab(s) and {spaces} space(s)")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndentError {
+    Inconsistent {
+        expected: Indent,
+        found: Indent,
+        position: Option<Position>,
+    },
+    Unexpected {
+        position: Option<Position>,
+    },
+    NoContext,
+    NoPosition,
+}
+
+impl Display for IndentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Inconsistent {
+                expected,
+                found,
+                position,
+            } => {
+                write!(f, "inconsistent use of tabs and spaces in indentation")?;
+                if let Some(position) = position {
+                    write!(f, " at {position}")?;
+                }
+                write!(f, ", expected {expected} found {found}")
+            }
+            Self::Unexpected { position } => {
+                write!(f, "unexpected indentation")?;
+                match position {
+                    Some(position) => write!(f, " at {position}"),
+                    None => Ok(()),
+                }
+            }
+            Self::NoContext => write!(f, "indentation requires a processing context"),
+            Self::NoPosition => write!(f, "indentation requires a source which tells positions"),
+        }
+    }
+}
+
+impl Error for IndentError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Indentation;
+
+impl Processor<char> for Indentation {
+    type Output = Indent;
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        if given.position().is_some_and(|x| !x.at_line_start()) {
+            return mismatch(given);
+        }
+        done(measure(&mut given), given)
+    }
+}
+
+impl Describe for Indentation {
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        Expr::special("indentation")
+    }
+}
+
+#[derive(Clone)]
+pub struct IndentedBlock<P>(P);
+
+impl<P> Processor<char> for IndentedBlock<P>
+where
+    P: Processor<char>,
+{
+    type Output = Vec<P::Output>;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        guarded("indented_block", given, |mut given| {
+            let fallback = given.snapshot();
+            let parent = match given.context() {
+                Some(context) => context.indent().cloned().unwrap_or_default(),
+                None => return err(IndentError::NoContext),
+            };
+            let (indent, position) = match next_line(&mut given)? {
+                Line::Indented(indent, position) => (indent, position),
+                Line::Continued => return rewind(given, fallback),
+                Line::Ended => return ran_out(given, fallback, Needed::Unknown),
+            };
+            match indent.compare(&parent) {
+                Some(Ordering::Greater) => (),
+                Some(_) => return rewind(given, fallback),
+                None => {
+                    return err(IndentError::Inconsistent {
+                        expected: parent,
+                        found: indent,
+                        position,
+                    })
+                }
+            }
+            if let Some(context) = given.context() {
+                context.push_indent(indent.clone());
+            }
+            let mut outputs = Vec::new();
+            let mut rest = match self.0.process(given)? {
+                Status::Done(output, rest) => {
+                    outputs.push(output);
+                    rest
+                }
+                Status::Mismatch(rest) => return rewind(rest, fallback),
+                Status::Incomplete(needed) => return incomplete(needed),
+            };
+            loop {
+                cancelled(&mut rest)?;
+                let line_start = rest.snapshot();
+                let (found, position) = match next_line(&mut rest)? {
+                    Line::Indented(indent, position) => (indent, position),
+                    // More lines of the block might still arrive
+                    Line::Ended if rest.is_partial() => return incomplete(Needed::Unknown),
+                    Line::Continued | Line::Ended => {
+                        rest = rolled_back(rest, line_start)?;
+                        break;
+                    }
+                };
+                match found.compare(&indent) {
+                    Some(Ordering::Equal) => match self.0.process(rest)? {
+                        Status::Done(output, new_rest) => {
+                            outputs.push(output);
+                            rest = new_rest;
+                        }
+                        Status::Mismatch(new_rest) => {
+                            rest = rolled_back(new_rest, line_start)?;
+                            break;
+                        }
+                        Status::Incomplete(needed) => return incomplete(needed),
+                    },
+                    Some(Ordering::Less) => {
+                        rest = rolled_back(rest, line_start)?;
+                        break;
+                    }
+                    Some(Ordering::Greater) => return err(IndentError::Unexpected { position }),
+                    None => {
+                        return err(IndentError::Inconsistent {
+                            expected: indent,
+                            found,
+                            position,
+                        })
+                    }
+                }
+            }
+            if let Some(context) = rest.context() {
+                context.pop_indent();
+            }
+            done(outputs, rest)
+        })
+    }
+}
+
+impl<P> Describe for IndentedBlock<P>
+where
+    P: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        let line = self.0.describe(grammar);
+        let more = Expr::special("same indentation").then(line.clone());
+        let first = Expr::special("deeper indentation").then(line);
+        first.then(more.repeat(0, None))
+    }
+}
+
+#[derive(Clone)]
+pub struct SameIndent<P>(P);
+
+impl<P> Processor<char> for SameIndent<P>
+where
+    P: Processor<char>,
+{
+    type Output = P::Output;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        guarded("same_indent", given, |mut given| {
+            let fallback = given.snapshot();
+            let current = match given.context() {
+                Some(context) => context.indent().cloned().unwrap_or_default(),
+                None => return err(IndentError::NoContext),
+            };
+            let (found, position) = match next_line(&mut given)? {
+                Line::Indented(indent, position) => (indent, position),
+                Line::Continued => return rewind(given, fallback),
+                Line::Ended => return ran_out(given, fallback, Needed::Unknown),
+            };
+            match found.compare(&current) {
+                Some(Ordering::Equal) => match self.0.process(given)? {
+                    Status::Done(output, rest) => done(output, rest),
+                    Status::Mismatch(rest) => rewind(rest, fallback),
+                    Status::Incomplete(needed) => incomplete(needed),
+                },
+                Some(_) => rewind(given, fallback),
+                None => err(IndentError::Inconsistent {
+                    expected: current,
+                    found,
+                    position,
+                }),
+            }
+        })
+    }
+}
+
+impl<P> Describe for SameIndent<P>
+where
+    P: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        Expr::special("same indentation").then(self.0.describe(grammar))
+    }
+}
+
+pub fn indentation() -> Indentation {
+    Indentation
+}
+
+pub fn indented_block<P>(processor: P) -> IndentedBlock<P>
+where
+    P: Processor<char>,
+{
+    IndentedBlock(processor)
+}
+
+pub fn same_indent<P>(processor: P) -> SameIndent<P>
+where
+    P: Processor<char>,
+{
+    SameIndent(processor)
+}
+
+fn measure<S>(given: &mut S) -> Indent
+where
+    S: Source<Item = char>,
+{
+    let mut indent = String::new();
+    while let Some(next) = given.next_if(|x| *x == ' ' || *x == '\t') {
+        indent.push(next);
+    }
+    Indent(indent)
+}
+
+enum Line {
+    Indented(Indent, Option<Position>),
+    // Content follows on the line which was already started
+    Continued,
+    Ended,
+}
+
+// Skips blank lines and measures the indentation of the next line with content,
+// what's left of a started line is only skipped if it's blank
+fn next_line<S>(given: &mut S) -> Result<Line, IndentError>
+where
+    S: Source<Item = char>,
+{
+    loop {
+        let position = given.position().ok_or(IndentError::NoPosition)?;
+        let indent = measure(given);
+        match given.peek() {
+            None => return Ok(Line::Ended),
+            Some('\n') => {
+                given.next();
+            }
+            Some('\r') => {
+                given.next();
+                given.next_if_eq(&'\n');
+            }
+            Some(_) if position.at_line_start() => {
+                return Ok(Line::Indented(indent, Some(position)))
+            }
+            Some(_) => return Ok(Line::Continued),
+        }
+    }
+}
+
+fn rolled_back<S>(mut given: S, to: S::Snapshot) -> Result<S, S::RollBackErr>
+where
+    S: Source,
+{
+    given.roll_back(to)?;
+    Ok(given)
+}
@@ -0,0 +1,305 @@
+use std::{
+    convert::Infallible,
+    error::Error,
+    fmt::{self, Debug, Display},
+};
+
+use crate::{
+    done,
+    grammar::{Describe, Expr, Grammar},
+    processed::{self, unmatched},
+    source::{Located, Position, Source, Span},
+    Processed, Processor, Status,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Token<K> {
+    pub kind: K,
+    pub text: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LexError(Position);
+
+impl LexError {
+    pub fn position(&self) -> Position {
+        self.0
+    }
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no token rule matches the input at {}", self.0)
+    }
+}
+
+impl Error for LexError {}
+
+// Processors are generic over the source, so the rules are erased for one concrete source
+trait Rule<S, K> {
+    fn apply(&mut self, given: S) -> Processed<Option<K>, S>;
+}
+
+struct Emit<P>(P);
+
+impl<P, S, K> Rule<S, K> for Emit<P>
+where
+    P: Processor<char, Output = K>,
+    S: Source<Item = char>,
+{
+    fn apply(&mut self, given: S) -> Processed<Option<K>, S> {
+        Ok(self.0.process(given)?.map(Some))
+    }
+}
+
+struct Skip<P>(P);
+
+impl<P, S, K> Rule<S, K> for Skip<P>
+where
+    P: Processor<char>,
+    S: Source<Item = char>,
+{
+    fn apply(&mut self, given: S) -> Processed<Option<K>, S> {
+        Ok(self.0.process(given)?.map(|_| None))
+    }
+}
+
+pub struct Lexer<'a, S, K> {
+    rules: Vec<Box<dyn Rule<Located<S>, K> + 'a>>,
+}
+
+impl<'a, S, K> Lexer<'a, S, K>
+where
+    S: Source<Item = char>,
+{
+    pub fn new() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    // The longest match wins, rules added first win when matches are equally long
+    pub fn rule<P>(mut self, processor: P) -> Self
+    where
+        P: Processor<char, Output = K> + 'a,
+    {
+        self.rules.push(Box::new(Emit(processor)));
+        self
+    }
+
+    pub fn skip<P>(mut self, processor: P) -> Self
+    where
+        P: Processor<char> + 'a,
+    {
+        self.rules.push(Box::new(Skip(processor)));
+        self
+    }
+
+    pub fn tokens(&mut self, source: S) -> Tokens<'_, 'a, S, K> {
+        Tokens {
+            lexer: self,
+            source: Some(Located::new(source)),
+            buffer: Vec::new(),
+            idx: 0,
+            incomplete: false,
+            error: None,
+        }
+    }
+
+    fn lex(&mut self, mut given: Located<S>) -> Result<Lexed<K, S>, processed::Error> {
+        loop {
+            let start = given.snapshot();
+            let start_position = given.position().unwrap_or_default();
+            if given.peek().is_none() {
+                return Ok(Lexed::End(given));
+            }
+            let mut best: Option<(usize, Option<K>)> = None;
+            for rule in self.rules.iter_mut() {
+                let fallback = given.snapshot();
+                given = match rule.apply(given)? {
+                    Status::Done(kind, mut rest) => {
+                        // A match running up to the end of partial input might still grow
+                        if rest.is_partial() && rest.peek().is_none() {
+                            rest.roll_back(start)?;
+                            return Ok(Lexed::Incomplete(Some(rest)));
+                        }
+                        let end = rest.position().unwrap_or_default().offset;
+                        let length = end - start_position.offset;
+                        if length > 0 && best.as_ref().is_none_or(|(best, _)| length > *best) {
+                            best = Some((length, kind));
+                        }
+                        rest.roll_back(fallback)?;
+                        rest
+                    }
+                    Status::Mismatch(mut rest) => {
+                        rest.roll_back(fallback)?;
+                        rest
+                    }
+                    Status::Incomplete(_) => return Ok(Lexed::Incomplete(None)),
+                };
+            }
+            let (length, kind) = match best {
+                Some(best) => best,
+                None => return Err(LexError(start_position).into()),
+            };
+            given.roll_back(start)?;
+            let mut text = String::with_capacity(length);
+            for _ in 0..length {
+                text.extend(given.next());
+            }
+            if let Some(kind) = kind {
+                let span = Span::new(start_position.offset, start_position.offset + length);
+                return Ok(Lexed::Token(Token { kind, text, span }, given));
+            }
+        }
+    }
+}
+
+impl<S, K> Default for Lexer<'_, S, K>
+where
+    S: Source<Item = char>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+enum Lexed<K, S> {
+    Token(Token<K>, Located<S>),
+    End(Located<S>),
+    // A rule which ran out of partial input doesn't hand the source back
+    Incomplete(Option<Located<S>>),
+}
+
+pub struct Tokens<'l, 'a, S, K> {
+    lexer: &'l mut Lexer<'a, S, K>,
+    source: Option<Located<S>>,
+    buffer: Vec<Token<K>>,
+    idx: usize,
+    incomplete: bool,
+    error: Option<processed::Error>,
+}
+
+impl<S, K> Tokens<'_, '_, S, K>
+where
+    S: Source<Item = char>,
+{
+    fn fill(&mut self) -> bool {
+        if self.idx < self.buffer.len() {
+            return true;
+        }
+        let Some(source) = self.source.take() else {
+            return false;
+        };
+        match self.lexer.lex(source) {
+            Ok(Lexed::Token(token, rest)) => {
+                self.buffer.push(token);
+                self.source = Some(rest);
+                true
+            }
+            Ok(Lexed::End(rest)) => {
+                self.source = Some(rest);
+                false
+            }
+            Ok(Lexed::Incomplete(rest)) => {
+                self.incomplete = true;
+                self.source = rest;
+                false
+            }
+            Err(error) => {
+                self.error = Some(error);
+                false
+            }
+        }
+    }
+}
+
+impl<S, K> Source for Tokens<'_, '_, S, K>
+where
+    S: Source<Item = char>,
+    K: Clone,
+{
+    type Item = Token<K>;
+    type Snapshot = usize;
+    type RollBackErr = Infallible;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.fill() {
+            return None;
+        }
+        self.idx += 1;
+        self.buffer.get(self.idx - 1).cloned()
+    }
+
+    #[inline]
+    fn snapshot(&self) -> Self::Snapshot {
+        self.idx
+    }
+
+    #[inline]
+    fn roll_back(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        self.idx = to;
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Option<&Self::Item> {
+        if !self.fill() {
+            return None;
+        }
+        self.buffer.get(self.idx)
+    }
+
+    fn peek_mut(&mut self) -> Option<&mut Self::Item> {
+        if !self.fill() {
+            return None;
+        }
+        self.buffer.get_mut(self.idx)
+    }
+
+    // The tokens are only partial as long as they could get more of them
+    #[inline]
+    fn is_partial(&self) -> bool {
+        self.idx == self.buffer.len()
+            && (self.incomplete || self.source.as_ref().is_some_and(Located::is_partial))
+    }
+
+    #[inline]
+    fn take_error(&mut self) -> Option<processed::Error> {
+        self.error.take()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Kind<K>(K);
+
+impl<K> Processor<Token<K>> for Kind<K>
+where
+    K: PartialEq,
+{
+    type Output = Token<K>;
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = Token<K>>,
+    {
+        match given.next_if(|token| token.kind == self.0) {
+            Some(token) => done(token, given),
+            None => unmatched(given),
+        }
+    }
+}
+
+impl<K> Describe for Kind<K>
+where
+    K: Debug,
+{
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        Expr::special(format!("{:?}", self.0))
+    }
+}
+
+pub fn kind<K>(kind: K) -> Kind<K>
+where
+    K: PartialEq,
+{
+    Kind(kind)
+}
@@ -1,18 +1,44 @@
-pub use processed::{done, err, mismatch};
+pub use processed::{done, err, incomplete, mismatch};
 
-use context::With;
+use std::{any::Any, marker::PhantomData};
+
+use context::{guarded, MissingState, With};
+use cst::{Node, SyntaxKind};
+use grammar::{Describe, Expr, Grammar};
 use processed::{Processed, Status};
 use source::Source;
+use trivia::{CapturedLexeme, Lexeme, Padded};
 
+// This mimics the log crate to avoid checking for the feature available
+#[macro_use]
+mod log;
+
+#[cfg(feature = "async")]
+pub mod async_io;
+pub mod binary;
+pub mod bits;
 pub mod collections;
 pub mod context;
+pub mod cst;
+pub mod encoding;
+pub mod grammar;
+pub mod incremental;
+pub mod indent;
+pub mod lexer;
+pub mod morph;
+pub mod parallel;
 pub mod processed;
 pub mod processors;
+#[cfg(feature = "profiling")]
+pub mod profile;
+pub mod search;
 pub mod source;
+pub mod source_map;
+pub mod trace;
+pub mod trivia;
+#[cfg(feature = "unicode")]
+pub mod unicode;
 
-// This mimics the log crate to avoid checking for the feature available
-#[macro_use]
-mod log;
 mod macros;
 
 pub trait Processor<I> {
@@ -32,6 +58,32 @@ pub trait Processor<I> {
         }
     }
 
+    fn map_with_state<St, F, R>(self, map: F) -> MapWithState<Self, F, St>
+    where
+        Self: Sized,
+        St: Any,
+        F: FnMut(Self::Output, &mut St) -> R,
+    {
+        MapWithState {
+            processor: self,
+            map,
+            state: PhantomData,
+        }
+    }
+
+    fn verify_with_state<St, F>(self, verify: F) -> VerifyWithState<Self, F, St>
+    where
+        Self: Sized,
+        St: Any,
+        F: FnMut(&Self::Output, &St) -> bool,
+    {
+        VerifyWithState {
+            processor: self,
+            verify,
+            state: PhantomData,
+        }
+    }
+
     fn replace<T>(self, with: T) -> CopyReplace<Self, T>
     where
         Self: Sized,
@@ -113,6 +165,52 @@ pub trait Processor<I> {
         Or(self, other)
     }
 
+    fn lexeme(self) -> Lexeme<Self>
+    where
+        Self: Sized,
+    {
+        trivia::lexeme(self)
+    }
+
+    fn lexeme_captured(self) -> CapturedLexeme<Self>
+    where
+        Self: Sized,
+    {
+        trivia::lexeme_captured(self)
+    }
+
+    fn padded(self) -> Padded<Self>
+    where
+        Self: Sized,
+    {
+        trivia::padded(self)
+    }
+
+    // Traces show the name instead of the combinators it's made of
+    fn named(self, name: &'static str) -> Named<Self>
+    where
+        Self: Sized,
+    {
+        Named {
+            processor: self,
+            name,
+        }
+    }
+
+    fn node(self, kind: SyntaxKind) -> Node<Self>
+    where
+        Self: Sized,
+    {
+        cst::node(self, kind)
+    }
+
+    fn token(self, kind: SyntaxKind) -> cst::Token<Self>
+    where
+        Self: Sized,
+    {
+        cst::token(self, kind)
+    }
+
     // TODO implement
     // fn start_chain(self) -> Chain<Self>
     // where
@@ -144,6 +242,42 @@ where
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct Named<P> {
+    processor: P,
+    name: &'static str,
+}
+
+impl<P> Named<P> {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<P, I> Processor<I> for Named<P>
+where
+    P: Processor<I>,
+{
+    type Output = P::Output;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = I>,
+    {
+        guarded(self.name, given, |given| self.processor.process(given))
+    }
+}
+
+impl<P> Describe for Named<P>
+where
+    P: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        grammar.define(self.name, &self.processor)
+    }
+}
+
+#[derive(Clone)]
 pub struct Map<P, F> {
     processor: P,
     map: F,
@@ -160,11 +294,139 @@ where
     where
         S: Source<Item = I>,
     {
-        let status = self.processor.process(given)?;
-        Ok(status.map(|inner| (self.map)(inner)))
+        guarded("map", given, |given| {
+            let status = self.processor.process(given)?;
+            Ok(status.map(|inner| (self.map)(inner)))
+        })
+    }
+}
+
+impl<P, F> Describe for Map<P, F>
+where
+    P: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        self.processor.describe(grammar)
+    }
+}
+
+pub struct MapWithState<P, F, St> {
+    processor: P,
+    map: F,
+    state: PhantomData<fn(&mut St)>,
+}
+
+// Deriving would require the state to be Clone as well
+impl<P, F, St> Clone for MapWithState<P, F, St>
+where
+    P: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            processor: self.processor.clone(),
+            map: self.map.clone(),
+            state: PhantomData,
+        }
     }
 }
 
+impl<P, I, F, St, R> Processor<I> for MapWithState<P, F, St>
+where
+    P: Processor<I>,
+    St: Any,
+    F: FnMut(P::Output, &mut St) -> R,
+{
+    type Output = R;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = I>,
+    {
+        guarded("map_with_state", given, |given| {
+            let (output, mut rest) = try_done!(self.processor.process(given));
+            match context::state(&mut rest) {
+                Some(state) => {
+                    let mapped = (self.map)(output, state);
+                    done(mapped, rest)
+                }
+                None => err(MissingState::of::<St>()),
+            }
+        })
+    }
+}
+
+impl<P, F, St> Describe for MapWithState<P, F, St>
+where
+    P: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        self.processor.describe(grammar)
+    }
+}
+
+pub struct VerifyWithState<P, F, St> {
+    processor: P,
+    verify: F,
+    state: PhantomData<fn(&St)>,
+}
+
+impl<P, F, St> Clone for VerifyWithState<P, F, St>
+where
+    P: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            processor: self.processor.clone(),
+            verify: self.verify.clone(),
+            state: PhantomData,
+        }
+    }
+}
+
+impl<P, I, F, St> Processor<I> for VerifyWithState<P, F, St>
+where
+    P: Processor<I>,
+    St: Any,
+    F: FnMut(&P::Output, &St) -> bool,
+{
+    type Output = P::Output;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = I>,
+    {
+        guarded("verify_with_state", given, |given| {
+            let fallback = given.snapshot();
+            let (output, mut rest) = try_done!(self.processor.process(given));
+            let verified = match context::state(&mut rest) {
+                Some(state) => (self.verify)(&output, state),
+                None => return err(MissingState::of::<St>()),
+            };
+            if verified {
+                done(output, rest)
+            } else {
+                match rest.roll_back(fallback) {
+                    Ok(_) => mismatch(rest),
+                    Err(error) => err(error),
+                }
+            }
+        })
+    }
+}
+
+// What the state allows can't be told from the outside
+impl<P, F, St> Describe for VerifyWithState<P, F, St>
+where
+    P: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        self.processor.describe(grammar)
+    }
+}
+
+#[derive(Clone)]
 pub struct CopyReplace<P, T>(P, T);
 
 impl<P, I, T> Processor<I> for CopyReplace<P, T>
@@ -178,10 +440,22 @@ where
     where
         S: Source<Item = I>,
     {
-        Ok(self.0.process(given)?.map(|_| self.1))
+        guarded("replace", given, |given| {
+            Ok(self.0.process(given)?.map(|_| self.1))
+        })
+    }
+}
+
+impl<P, T> Describe for CopyReplace<P, T>
+where
+    P: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        self.0.describe(grammar)
     }
 }
 
+#[derive(Clone)]
 pub struct Take<P> {
     processor: P,
     current: usize,
@@ -198,16 +472,37 @@ where
     where
         S: Source<Item = I>,
     {
-        if self.current < self.target {
-            self.current += 1;
-            self.processor.process(given)
-        } else {
-            self.current = 0;
-            mismatch(given)
+        guarded("take", given, |given| {
+            if self.current < self.target {
+                self.current += 1;
+                self.processor.process(given)
+            } else {
+                self.current = 0;
+                mismatch(given)
+            }
+        })
+    }
+}
+
+// One call runs the processor at most once, the target only bounds a fold over it
+impl<P> Describe for Take<P>
+where
+    P: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        match self.target {
+            0 => Expr::special("nothing"),
+            _ => self.processor.describe(grammar).repeat(0, Some(1)),
         }
     }
+
+    fn describe_repeated(&self, grammar: &mut Grammar) -> Expr {
+        let expr = self.processor.describe(grammar);
+        expr.repeat(0, Some(self.target))
+    }
 }
 
+#[derive(Clone)]
 pub struct TakeWhile<P, F>(P, F);
 
 impl<P, I, F> Processor<I> for TakeWhile<P, F>
@@ -217,19 +512,32 @@ where
 {
     type Output = P::Output;
 
-    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
     where
         S: Source<Item = I>,
     {
-        let peeked = try_peek!(given);
-        if (self.1)(peeked) {
-            self.0.process(given)
-        } else {
-            mismatch(given)
-        }
+        guarded("take_while", given, |mut given| {
+            let peeked = try_peek!(given);
+            if (self.1)(peeked) {
+                self.0.process(given)
+            } else {
+                mismatch(given)
+            }
+        })
+    }
+}
+
+// The predicate only looks ahead, what's consumed is up to the processor
+impl<P, F> Describe for TakeWhile<P, F>
+where
+    P: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        self.0.describe(grammar)
     }
 }
 
+#[derive(Clone)]
 pub struct Fold<P, A, F> {
     processor: P,
     accum: A,
@@ -248,20 +556,34 @@ where
     where
         S: Source<Item = I>,
     {
-        let mut state = (self.accum)();
-        let mut rest = given;
-        loop {
-            rest = match self.processor.process(rest)? {
-                Status::Done(output, new_rest) => {
-                    state = (self.fold)(state, output);
-                    new_rest
+        guarded("fold", given, |given| {
+            let mut state = (self.accum)();
+            let mut rest = given;
+            loop {
+                context::repeat(&mut rest)?;
+                rest = match self.processor.process(rest)? {
+                    Status::Done(output, new_rest) => {
+                        state = (self.fold)(state, output);
+                        new_rest
+                    }
+                    Status::Mismatch(new_rest) => return done(state, new_rest),
+                    Status::Incomplete(needed) => return incomplete(needed),
                 }
-                Status::Mismatch(new_rest) => return done(state, new_rest),
             }
-        }
+        })
+    }
+}
+
+impl<P, A, F> Describe for Fold<P, A, F>
+where
+    P: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        self.processor.describe_repeated(grammar)
     }
 }
 
+#[derive(Clone)]
 pub struct Zip<A, B>(A, B);
 
 impl<A, B, I> Processor<I> for Zip<A, B>
@@ -275,15 +597,26 @@ where
     where
         S: Source<Item = I>,
     {
-        match try_done!(self.0.process(given)) {
-            (first, rest) => {
-                let second = self.1.process(rest)?;
-                Ok(second.map(|inner| (first, inner)))
-            }
-        }
+        guarded("zip", given, |given| {
+            let (first, rest) = try_done!(self.0.process(given));
+            let second = self.1.process(rest)?;
+            Ok(second.map(|inner| (first, inner)))
+        })
+    }
+}
+
+impl<A, B> Describe for Zip<A, B>
+where
+    A: Describe,
+    B: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        let first = self.0.describe(grammar);
+        first.then(self.1.describe(grammar))
     }
 }
 
+#[derive(Clone)]
 pub struct Ignore<L, R>(L, R);
 
 impl<L, R, I> Processor<I> for Ignore<L, R>
@@ -297,13 +630,26 @@ where
     where
         S: Source<Item = I>,
     {
-        let fallback = given.snapshot();
-        match try_done!(self.0.process(given)) {
-            (_, rest) => rollback_if_process_fail(fallback, &mut self.1, rest),
-        }
+        guarded("ignore", given, |given| {
+            let fallback = given.snapshot();
+            let (_, rest) = try_done!(self.0.process(given));
+            rollback_if_process_fail(fallback, &mut self.1, rest)
+        })
+    }
+}
+
+impl<L, R> Describe for Ignore<L, R>
+where
+    L: Describe,
+    R: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        let first = self.0.describe(grammar);
+        first.then(self.1.describe(grammar))
     }
 }
 
+#[derive(Clone)]
 pub struct IgnoreNext<L, R>(L, R);
 
 impl<L, R, I> Processor<I> for IgnoreNext<L, R>
@@ -317,16 +663,30 @@ where
     where
         S: Source<Item = I>,
     {
-        let fallback = given.snapshot();
-        match try_done!(self.0.process(given)) {
-            (output, rest) => match rollback_if_process_fail(fallback, &mut self.1, rest)? {
+        guarded("ignore_next", given, |given| {
+            let fallback = given.snapshot();
+            let (output, rest) = try_done!(self.0.process(given));
+            match rollback_if_process_fail(fallback, &mut self.1, rest)? {
                 Status::Done(_, rest) => done(output, rest),
                 Status::Mismatch(rest) => mismatch(rest),
-            },
-        }
+                Status::Incomplete(needed) => incomplete(needed),
+            }
+        })
+    }
+}
+
+impl<L, R> Describe for IgnoreNext<L, R>
+where
+    L: Describe,
+    R: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        let first = self.0.describe(grammar);
+        first.then(self.1.describe(grammar))
     }
 }
 
+#[derive(Clone)]
 pub struct Or<A, B>(A, B);
 
 impl<A, B, I, O> Processor<I> for Or<A, B>
@@ -340,11 +700,30 @@ where
     where
         S: Source<Item = I>,
     {
-        let status = self.0.process(given)?;
-        match status {
-            Status::Done(_, _) => Ok(status),
-            Status::Mismatch(rest) => self.1.process(rest),
-        }
+        guarded("or", given, |given| {
+            let (alternative, status) = match self.0.process(given)? {
+                Status::Mismatch(rest) => (2, self.1.process(rest)?),
+                status => (1, status),
+            };
+            match status {
+                Status::Done(output, mut rest) => {
+                    context::taken(&mut rest, alternative);
+                    done(output, rest)
+                }
+                status => Ok(status),
+            }
+        })
+    }
+}
+
+impl<A, B> Describe for Or<A, B>
+where
+    A: Describe,
+    B: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        let first = self.0.describe(grammar);
+        first.or(self.1.describe(grammar))
     }
 }
 
@@ -363,5 +742,6 @@ where
             Ok(_) => mismatch(rest),
             Err(error) => err(error),
         },
+        Status::Incomplete(needed) => incomplete(needed),
     }
 }
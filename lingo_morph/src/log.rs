@@ -3,48 +3,45 @@
 
 macro_rules! trace {
     ($($args:tt)+) => {{
-        if cfg!(feature = "logging") {
-            _log::trace!($($args)+);
-        }
+        #[cfg(feature = "logging")]
+        _log::trace!($($args)+);
     }};
 }
 
 macro_rules! debug {
     ($($args:tt)+) => {{
-        if cfg!(feature = "logging") {
-            _log::debug!($($args)+);
-        }
+        #[cfg(feature = "logging")]
+        _log::debug!($($args)+);
     }};
 }
 
 macro_rules! info {
     ($($args:tt)+) => {{
-        if cfg!(feature = "logging") {
-            _log::info!($($args)+);
-        }
+        #[cfg(feature = "logging")]
+        _log::info!($($args)+);
     }};
 }
 
 macro_rules! warn {
     ($($args:tt)+) => {{
-        if cfg!(feature = "logging") {
-            _log::warn!($($args)+);
-        }
+        #[cfg(feature = "logging")]
+        _log::warn!($($args)+);
     }};
 }
 
 macro_rules! error {
     ($($args:tt)+) => {{
-        if cfg!(feature = "logging") {
-            _log::error!($($args)+);
-        }
+        #[cfg(feature = "logging")]
+        _log::error!($($args)+);
     }};
 }
 
 macro_rules! log_enabled {
     ($($args:tt)+) => {{
-        if cfg!(feature = "logging") {
-            _log::log_enabled!($($args)+);
-        }
+        #[cfg(feature = "logging")]
+        let enabled = _log::log_enabled!($($args)+);
+        #[cfg(not(feature = "logging"))]
+        let enabled = false;
+        enabled
     }};
 }
@@ -4,6 +4,9 @@ macro_rules! try_done {
         match $processed? {
             $crate::processed::Status::Done(output, rest) => (output, rest),
             $crate::processed::Status::Mismatch(rest) => return $crate::processed::mismatch(rest),
+            $crate::processed::Status::Incomplete(needed) => {
+                return $crate::processed::incomplete(needed)
+            }
         }
     };
 }
@@ -23,7 +26,7 @@ macro_rules! try_peek {
     ($source:expr) => {
         match $source.peek() {
             Some(val) => val,
-            None => return $crate::processed::mismatch($source),
+            None => return $crate::processed::unmatched($source),
         }
     };
 }
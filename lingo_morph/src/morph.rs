@@ -0,0 +1,359 @@
+use std::{any::Any, io, mem};
+
+use crate::{
+    context::{cancelled, offset, Context, Contextual, Diagnostic, ProcessingFailed},
+    processed,
+    source::{Position, Source, Span},
+    source_map::SourceMap,
+    Processor, Status,
+};
+
+// Amount of bytes collected before they get handed to the writer
+const FLUSH_AT: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Found<'a, O> {
+    pub text: &'a str,
+    pub output: &'a O,
+    pub span: Span,
+}
+
+pub trait Replacement<O> {
+    fn append(&mut self, found: &Found<'_, O>, into: &mut String);
+}
+
+impl<O> Replacement<O> for &str {
+    fn append(&mut self, _: &Found<'_, O>, into: &mut String) {
+        into.push_str(self);
+    }
+}
+
+impl<O> Replacement<O> for String {
+    fn append(&mut self, _: &Found<'_, O>, into: &mut String) {
+        into.push_str(self);
+    }
+}
+
+pub struct Func<F>(F);
+
+impl<F, O> Replacement<O> for Func<F>
+where
+    F: FnMut(&Found<'_, O>) -> String,
+{
+    fn append(&mut self, found: &Found<'_, O>, into: &mut String) {
+        into.push_str(&(self.0)(found));
+    }
+}
+
+pub fn func<F, O>(replace: F) -> Func<F>
+where
+    F: FnMut(&Found<'_, O>) -> String,
+{
+    Func(replace)
+}
+
+// Flattens an output into the numbered captures a template refers to, starting at $1
+pub trait Captures {
+    fn captures(&self, into: &mut Vec<String>);
+}
+
+macro_rules! display_captures {
+    ($($ty:ty),+) => {
+        $(
+            impl Captures for $ty {
+                fn captures(&self, into: &mut Vec<String>) {
+                    into.push(self.to_string());
+                }
+            }
+        )+
+    };
+}
+
+display_captures!(
+    String, &str, char, bool, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32,
+    f64
+);
+
+impl Captures for () {
+    fn captures(&self, _: &mut Vec<String>) {}
+}
+
+impl<T> Captures for Option<T>
+where
+    T: Captures,
+{
+    fn captures(&self, into: &mut Vec<String>) {
+        match self {
+            Some(inner) => inner.captures(into),
+            None => into.push(String::new()),
+        }
+    }
+}
+
+macro_rules! tuple_captures {
+    ($($name:ident),+) => {
+        impl<$($name),+> Captures for ($($name,)+)
+        where
+            $($name: Captures,)+
+        {
+            #[allow(non_snake_case)]
+            fn captures(&self, into: &mut Vec<String>) {
+                let ($($name,)+) = self;
+                $($name.captures(into);)+
+            }
+        }
+    };
+}
+
+tuple_captures!(A);
+tuple_captures!(A, B);
+tuple_captures!(A, B, C);
+tuple_captures!(A, B, C, D);
+tuple_captures!(A, B, C, D, E);
+tuple_captures!(A, B, C, D, E, F);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Piece {
+    Literal(String),
+    Capture(usize),
+}
+
+// $0 is the matched text, $1 and onwards the captures of the output, $$ is a literal $
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Template(Vec<Piece>);
+
+impl Template {
+    pub fn new(template: &str) -> Self {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(next) = chars.next() {
+            if next != '$' {
+                literal.push(next);
+                continue;
+            }
+            if chars.next_if_eq(&'$').is_some() {
+                literal.push('$');
+                continue;
+            }
+            let braced = chars.next_if_eq(&'{').is_some();
+            let mut digits = String::new();
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                digits.push(digit);
+            }
+            let closed = !braced || chars.next_if_eq(&'}').is_some();
+            match digits.parse() {
+                Ok(idx) if closed => {
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(mem::take(&mut literal)));
+                    }
+                    pieces.push(Piece::Capture(idx));
+                }
+                _ => {
+                    literal.push('$');
+                    if braced {
+                        literal.push('{');
+                    }
+                    literal.push_str(&digits);
+                }
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        Self(pieces)
+    }
+}
+
+impl<O> Replacement<O> for Template
+where
+    O: Captures,
+{
+    fn append(&mut self, found: &Found<'_, O>, into: &mut String) {
+        let mut captures = Vec::new();
+        found.output.captures(&mut captures);
+        for piece in self.0.iter() {
+            match piece {
+                Piece::Literal(literal) => into.push_str(literal),
+                Piece::Capture(0) => into.push_str(found.text),
+                Piece::Capture(idx) => {
+                    if let Some(capture) = captures.get(idx - 1) {
+                        into.push_str(capture);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Keeps the characters read since it got cleared, so a match's text is known without rereading it
+struct Recorded<S> {
+    source: S,
+    text: String,
+}
+
+impl<S> Source for Recorded<S>
+where
+    S: Source<Item = char>,
+{
+    type Item = char;
+    type Snapshot = (S::Snapshot, usize);
+    type RollBackErr = S::RollBackErr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.source.next()?;
+        self.text.push(next);
+        Some(next)
+    }
+
+    #[inline]
+    fn snapshot(&self) -> Self::Snapshot {
+        (self.source.snapshot(), self.text.len())
+    }
+
+    #[inline]
+    fn roll_back(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        self.source.roll_back(to.0)?;
+        self.text.truncate(to.1);
+        Ok(())
+    }
+
+    #[inline]
+    fn restore(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        self.source.restore(to.0)?;
+        self.text.truncate(to.1);
+        Ok(())
+    }
+
+    #[inline]
+    fn peek(&mut self) -> Option<&Self::Item> {
+        self.source.peek()
+    }
+
+    #[inline]
+    fn peek_mut(&mut self) -> Option<&mut Self::Item> {
+        self.source.peek_mut()
+    }
+
+    #[inline]
+    fn context(&mut self) -> Option<&mut Context> {
+        self.source.context()
+    }
+
+    #[inline]
+    fn state(&mut self) -> Option<&mut dyn Any> {
+        self.source.state()
+    }
+
+    #[inline]
+    fn position(&self) -> Option<Position> {
+        self.source.position()
+    }
+
+    #[inline]
+    fn is_partial(&self) -> bool {
+        self.source.is_partial()
+    }
+
+    #[inline]
+    fn take_error(&mut self) -> Option<processed::Error> {
+        self.source.take_error()
+    }
+
+    #[inline]
+    fn as_slice(&self) -> Option<&[Self::Item]> {
+        self.source.as_slice()
+    }
+}
+
+pub(crate) fn run<P, S, R, W>(
+    processor: &mut P,
+    source: S,
+    context: Context,
+    replacement: &mut R,
+    writer: &mut W,
+    mut map: Option<&mut SourceMap>,
+) -> Result<Vec<Diagnostic>, ProcessingFailed>
+where
+    P: Processor<char>,
+    S: Source<Item = char>,
+    R: Replacement<P::Output>,
+    W: io::Write,
+{
+    let recorded = Recorded {
+        source,
+        text: String::new(),
+    };
+    let mut buffer = String::new();
+    let mut written = 0;
+    let mut current = Contextual::with_context(recorded, context);
+    let mut last_end = None;
+    let mut ended = false;
+    loop {
+        if current.peek().is_none() {
+            if let Some(error) = current.take_error() {
+                return Err(ProcessingFailed::DuringProcessing(error));
+            }
+            // The end of input is tried once more for an empty match
+            if ended {
+                break;
+            }
+            ended = true;
+        }
+        if cancelled(&mut current).is_err() {
+            return Err(ProcessingFailed::Cancelled(()));
+        }
+        current.source_mut().text.clear();
+        let fallback = current.snapshot();
+        let start = offset(&mut current);
+        let copy = match processor.process(current)? {
+            Status::Done(output, mut rest) => {
+                let end = offset(&mut rest);
+                current = rest;
+                // Same as find_iter, an empty match right after the previous match is skipped
+                if start != end || last_end != Some(start) {
+                    let text = mem::take(&mut current.source_mut().text);
+                    let span = Span::new(start, end);
+                    let found = Found {
+                        text: &text,
+                        output: &output,
+                        span,
+                    };
+                    let before = buffer.len();
+                    replacement.append(&found, &mut buffer);
+                    let replaced = &buffer[before..];
+                    if let Some(map) = map.as_deref_mut() {
+                        map.replace(span, written, &text, replaced);
+                    }
+                    written += replaced.chars().count();
+                    last_end = Some(end);
+                }
+                start == end
+            }
+            Status::Mismatch(mut rest) => {
+                rest.restore(fallback).map_err(processed::Error::from)?;
+                current = rest;
+                true
+            }
+            Status::Incomplete(needed) => return Err(ProcessingFailed::Incomplete(needed)),
+        };
+        if let Some(copied) = copy.then(|| current.next()).flatten() {
+            buffer.push(copied);
+            if let Some(map) = map.as_deref_mut() {
+                map.copy(start, written, copied);
+            }
+            written += 1;
+        }
+        if buffer.len() >= FLUSH_AT {
+            writer
+                .write_all(buffer.as_bytes())
+                .map_err(processed::Error::from)?;
+            buffer.clear();
+        }
+    }
+    writer
+        .write_all(buffer.as_bytes())
+        .and_then(|_| writer.flush())
+        .map_err(processed::Error::from)?;
+    Ok(current.into_parts().1.into_diagnostics())
+}
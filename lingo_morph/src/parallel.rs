@@ -0,0 +1,267 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    num::NonZeroUsize,
+    thread,
+};
+
+use crate::{
+    context::{offset, Contextual, Diagnosed, Diagnostic},
+    processed::{self, Needed},
+    source::{Borrowed, Source},
+    trivia::Trivia,
+    Processor, Status,
+};
+
+// Chunks handed out per thread, more of them evens out records of uneven cost
+const CHUNKS_PER_THREAD: usize = 4;
+
+#[derive(Debug)]
+pub enum ParallelError {
+    Failed {
+        offset: usize,
+        error: processed::Error,
+    },
+    Mismatch {
+        offset: usize,
+    },
+    NoProgress {
+        offset: usize,
+    },
+    Incomplete {
+        offset: usize,
+        needed: Needed,
+    },
+}
+
+impl ParallelError {
+    // Offset into the whole input where the failing record starts
+    pub fn offset(&self) -> usize {
+        match self {
+            Self::Failed { offset, .. }
+            | Self::Mismatch { offset }
+            | Self::NoProgress { offset }
+            | Self::Incomplete { offset, .. } => *offset,
+        }
+    }
+}
+
+impl Display for ParallelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Failed { offset, error } => write!(f, "{error} at {offset}"),
+            Self::Mismatch { offset } => write!(f, "no record matched at {offset}"),
+            Self::NoProgress { offset } => write!(f, "the processor consumed nothing at {offset}"),
+            Self::Incomplete { offset, needed } => write!(f, "{needed} at {offset}"),
+        }
+    }
+}
+
+impl Error for ParallelError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Failed { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+type Chunk<O> = Result<(Vec<O>, Vec<Diagnostic>), ParallelError>;
+
+// Errors can't cross threads, a chunk which failed is left empty
+type Finished<O> = Option<(Vec<O>, Vec<Diagnostic>)>;
+
+// Runs a clone of the processor over every chunk between two boundaries found by the splitter
+pub struct Parallel<'a, T, P, D> {
+    input: &'a [T],
+    processor: P,
+    splitter: D,
+    threads: usize,
+    trivia: Option<Trivia>,
+}
+
+impl<'a, T, P, D> Parallel<'a, T, P, D>
+where
+    T: Clone + Sync,
+    P: Processor<T> + Clone + Send,
+    P::Output: Send,
+    D: Processor<T>,
+{
+    pub fn new(input: &'a [T], processor: P, splitter: D) -> Self {
+        Self {
+            input,
+            processor,
+            splitter,
+            threads: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            trivia: None,
+        }
+    }
+
+    // With the rayon feature the global pool is used, the amount only decides how the input is chunked
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn trivia(mut self, trivia: Trivia) -> Self {
+        self.trivia = Some(trivia);
+        self
+    }
+
+    pub fn run(mut self) -> Result<Diagnosed<Vec<P::Output>>, ParallelError> {
+        let bounds = self.boundaries()?;
+        let chunks = bounds.windows(2).map(|x| (x[0], x[1])).collect::<Vec<_>>();
+        let mut output = Vec::new();
+        let mut diagnostics = Vec::new();
+        let finished = self.spread(&chunks);
+        for (&(start, end), finished) in chunks.iter().zip(finished) {
+            // The failed chunk is run again on this thread to get hold of its error
+            let (outputs, found) = match finished {
+                Some(finished) => finished,
+                None => {
+                    let (input, trivia) = (self.input, self.trivia.as_ref());
+                    chunk(input, trivia, &mut self.processor.clone(), start, end)?
+                }
+            };
+            output.extend(outputs);
+            diagnostics.extend(found);
+        }
+        Ok(Diagnosed {
+            output,
+            diagnostics,
+        })
+    }
+
+    // Splits right after a splitter match, looked for from evenly spaced offsets onwards
+    fn boundaries(&mut self) -> Result<Vec<usize>, ParallelError> {
+        let len = self.input.len();
+        let target = len.div_ceil(self.threads * CHUNKS_PER_THREAD).max(1);
+        let mut bounds = vec![0];
+        let mut at = target;
+        while at < len {
+            match self.split_after(at)? {
+                Some(bound) if bound < len => {
+                    bounds.push(bound);
+                    at = bound + target;
+                }
+                _ => break,
+            }
+        }
+        bounds.push(len);
+        Ok(bounds)
+    }
+
+    fn split_after(&mut self, from: usize) -> Result<Option<usize>, ParallelError> {
+        for idx in from..self.input.len() {
+            let given = Borrowed::from(&self.input[idx..]);
+            let processed = self
+                .splitter
+                .process(given)
+                .map_err(|error| ParallelError::Failed { offset: idx, error })?;
+            if let Status::Done(_, rest) = processed {
+                let end = self.input.len() - rest.remaining().len();
+                if end > idx {
+                    return Ok(Some(end));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    fn spread(&self, chunks: &[(usize, usize)]) -> Vec<Finished<P::Output>> {
+        use std::{
+            panic,
+            sync::atomic::{AtomicUsize, Ordering},
+        };
+
+        let (input, trivia) = (self.input, self.trivia.as_ref());
+        let next = AtomicUsize::new(0);
+        let mut results = chunks.iter().map(|_| None).collect::<Vec<_>>();
+        thread::scope(|scope| {
+            let workers = (0..self.threads.min(chunks.len())).map(|_| {
+                let mut processor = self.processor.clone();
+                let next = &next;
+                scope.spawn(move || {
+                    let mut finished = Vec::new();
+                    loop {
+                        let idx = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(&(start, end)) = chunks.get(idx) else {
+                            break finished;
+                        };
+                        let processed = chunk(input, trivia, &mut processor, start, end);
+                        finished.push((idx, processed.ok()));
+                    }
+                })
+            });
+            for worker in workers.collect::<Vec<_>>() {
+                // A worker which panicked brings the panic over to this thread
+                let finished = worker
+                    .join()
+                    .unwrap_or_else(|panic| panic::resume_unwind(panic));
+                for (idx, processed) in finished {
+                    results[idx] = processed;
+                }
+            }
+        });
+        results
+    }
+
+    #[cfg(feature = "rayon")]
+    fn spread(&self, chunks: &[(usize, usize)]) -> Vec<Finished<P::Output>> {
+        use rayon::prelude::*;
+
+        let (input, trivia) = (self.input, self.trivia.as_ref());
+        chunks
+            .par_iter()
+            .map_with(self.processor.clone(), |processor, (start, end)| {
+                chunk(input, trivia, processor, *start, *end).ok()
+            })
+            .collect()
+    }
+}
+
+fn chunk<T, P>(
+    input: &[T],
+    trivia: Option<&Trivia>,
+    processor: &mut P,
+    start: usize,
+    end: usize,
+) -> Chunk<P::Output>
+where
+    T: Clone,
+    P: Processor<T>,
+{
+    let given = Contextual::new(Borrowed::from(&input[start..end]));
+    let mut given = given.starting_at(start);
+    if let (Some(context), Some(trivia)) = (given.context(), trivia) {
+        context.set_trivia(trivia.clone());
+    }
+    let mut outputs = Vec::new();
+    while given.peek().is_some() {
+        let at = offset(&mut given);
+        let processed = processor
+            .process(given)
+            .map_err(|error| ParallelError::Failed { offset: at, error })?;
+        given = match processed {
+            Status::Done(output, mut rest) => {
+                if offset(&mut rest) == at {
+                    return Err(ParallelError::NoProgress { offset: at });
+                }
+                outputs.push(output);
+                rest
+            }
+            Status::Mismatch(_) => return Err(ParallelError::Mismatch { offset: at }),
+            Status::Incomplete(needed) => {
+                return Err(ParallelError::Incomplete { offset: at, needed })
+            }
+        };
+    }
+    if let Some(error) = given.take_error() {
+        return Err(ParallelError::Failed { offset: end, error });
+    }
+    let diagnostics = given
+        .context()
+        .map(|context| context.diagnostics().to_vec());
+    Ok((outputs, diagnostics.unwrap_or_default()))
+}
@@ -1,4 +1,9 @@
-use std::error;
+use std::{
+    error,
+    fmt::{self, Display},
+};
+
+use crate::source::Source;
 
 pub type Error = Box<dyn error::Error + 'static>;
 pub type PResult<I, R> = Result<Status<I, R>, Error>;
@@ -8,8 +13,27 @@ pub type Processed<O, R> = PResult<O, R>;
 pub enum Status<O, R> {
     Done(O, R),
     Mismatch(R),
+    // A partial source ran out, the processor has to be run again once more input arrived
+    Incomplete(Needed),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Needed {
+    Unknown,
+    Size(usize),
+}
+
+impl Display for Needed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown => write!(f, "more input is needed"),
+            Self::Size(size) => write!(f, "{size} more items are needed"),
+        }
+    }
 }
 
+impl error::Error for Needed {}
+
 impl<O, R> Status<O, R> {
     pub fn map<F, U>(self, mapper: F) -> Status<U, R>
     where
@@ -18,6 +42,7 @@ impl<O, R> Status<O, R> {
         match self {
             Self::Done(output, rest) => Status::Done(mapper(output), rest),
             Self::Mismatch(rest) => Status::Mismatch(rest),
+            Self::Incomplete(needed) => Status::Incomplete(needed),
         }
     }
 }
@@ -32,6 +57,23 @@ pub fn mismatch<O, R>(rest: R) -> Processed<O, R> {
     Ok(Status::Mismatch(rest))
 }
 
+#[inline]
+pub fn incomplete<O, R>(needed: Needed) -> Processed<O, R> {
+    Ok(Status::Incomplete(needed))
+}
+
+// For processors which failed to get an item, the end of a partial source is not a mismatch yet
+pub fn unmatched<O, S>(mut given: S) -> Processed<O, S>
+where
+    S: Source,
+{
+    if given.is_partial() && given.peek().is_none() {
+        incomplete(Needed::Unknown)
+    } else {
+        mismatch(given)
+    }
+}
+
 #[inline]
 pub fn err<O, R, E>(error: E) -> Processed<O, R>
 where
@@ -39,3 +81,24 @@ where
 {
     Err(error.into())
 }
+
+pub(crate) fn rewind<O, S>(mut given: S, to: S::Snapshot) -> Processed<O, S>
+where
+    S: Source,
+{
+    match given.roll_back(to) {
+        Ok(_) => mismatch(given),
+        Err(error) => err(error),
+    }
+}
+
+pub(crate) fn ran_out<O, S>(given: S, to: S::Snapshot, needed: Needed) -> Processed<O, S>
+where
+    S: Source,
+{
+    if given.is_partial() {
+        incomplete(needed)
+    } else {
+        rewind(given, to)
+    }
+}
@@ -3,7 +3,13 @@ use std::{
     ops::{Bound, RangeBounds},
 };
 
-use crate::{done, mismatch, source::Source, Processed, Processor};
+use crate::{
+    done,
+    grammar::{self, Describe, Expr, Grammar, Symbol},
+    processed::unmatched,
+    source::Source,
+    Processed, Processor,
+};
 
 pub type NoOp = Const<()>;
 
@@ -25,10 +31,16 @@ where
     }
 }
 
+impl<T> Describe for Const<T> {
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        Expr::Empty
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ConstWith<F>(F);
 
-impl<F, T> Processor<char> for ConstWith<F>
+impl<F, T, I> Processor<I> for ConstWith<F>
 where
     F: Fn() -> T,
 {
@@ -36,12 +48,18 @@ where
 
     fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
     where
-        S: Source<Item = char>,
+        S: Source<Item = I>,
     {
         done((self.0)(), given)
     }
 }
 
+impl<F> Describe for ConstWith<F> {
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        Expr::Empty
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(transparent)]
 pub struct Mut<T>(T);
@@ -78,6 +96,12 @@ where
     }
 }
 
+impl<T> Describe for Mut<T> {
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        Expr::Empty
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Any<I>(PhantomData<I>);
 
@@ -90,11 +114,17 @@ impl<I> Processor<I> for Any<I> {
     {
         match given.next() {
             Some(val) => done(val, given),
-            None => mismatch(given),
+            None => unmatched(given),
         }
     }
 }
 
+impl<I> Describe for Any<I> {
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        Expr::Any
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Char(char);
 
@@ -107,11 +137,17 @@ impl Processor<char> for Char {
     {
         match given.next_if_eq(&self.0) {
             Some(next) => done(next, given),
-            None => mismatch(given),
+            None => unmatched(given),
         }
     }
 }
 
+impl Describe for Char {
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        Expr::Char(self.0)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct CharRange {
     start: Bound<char>,
@@ -137,11 +173,173 @@ impl Processor<char> for CharRange {
     {
         match given.next_if(|item| self.contains(item)) {
             Some(next) => done(next, given),
-            None => mismatch(given),
+            None => unmatched(given),
+        }
+    }
+}
+
+impl Describe for CharRange {
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        char::range(self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Just<I>(I);
+
+impl<I> Processor<I> for Just<I>
+where
+    I: PartialEq,
+{
+    type Output = I;
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = I>,
+    {
+        match given.next_if_eq(&self.0) {
+            Some(next) => done(next, given),
+            None => unmatched(given),
         }
     }
 }
 
+impl<I> Describe for Just<I>
+where
+    I: Symbol,
+{
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        self.0.expr()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OneOf<I>(Vec<I>);
+
+impl<I> Processor<I> for OneOf<I>
+where
+    I: PartialEq,
+{
+    type Output = I;
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = I>,
+    {
+        match given.next_if(|item| self.0.contains(item)) {
+            Some(next) => done(next, given),
+            None => unmatched(given),
+        }
+    }
+}
+
+impl<I> Describe for OneOf<I>
+where
+    I: Symbol,
+{
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        grammar::one_of(&self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NoneOf<I>(Vec<I>);
+
+impl<I> Processor<I> for NoneOf<I>
+where
+    I: PartialEq,
+{
+    type Output = I;
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = I>,
+    {
+        match given.next_if(|item| !self.0.contains(item)) {
+            Some(next) => done(next, given),
+            None => unmatched(given),
+        }
+    }
+}
+
+impl<I> Describe for NoneOf<I>
+where
+    I: Symbol,
+{
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        let except = grammar::one_of(&self.0);
+        Expr::Except(Box::new(Expr::Any), Box::new(except))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Satisfy<F>(F);
+
+impl<F, I> Processor<I> for Satisfy<F>
+where
+    F: FnMut(&I) -> bool,
+{
+    type Output = I;
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = I>,
+    {
+        match given.next_if(&mut self.0) {
+            Some(next) => done(next, given),
+            None => unmatched(given),
+        }
+    }
+}
+
+impl<F> Describe for Satisfy<F> {
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        Expr::special("satisfying a predicate")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range<I> {
+    start: Bound<I>,
+    end: Bound<I>,
+}
+
+impl<I> RangeBounds<I> for Range<I> {
+    fn start_bound(&self) -> Bound<&I> {
+        self.start.as_ref()
+    }
+
+    fn end_bound(&self) -> Bound<&I> {
+        self.end.as_ref()
+    }
+}
+
+impl<I> Processor<I> for Range<I>
+where
+    I: PartialOrd,
+{
+    type Output = I;
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = I>,
+    {
+        match given.next_if(|item| self.contains(item)) {
+            Some(next) => done(next, given),
+            None => unmatched(given),
+        }
+    }
+}
+
+impl<I> Describe for Range<I>
+where
+    I: Symbol,
+{
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        I::range(self)
+    }
+}
+
 pub fn no_op() -> NoOp {
     constant(())
 }
@@ -165,6 +363,47 @@ pub fn any<I>() -> Any<I> {
     Any(PhantomData)
 }
 
+pub fn just<I>(item: I) -> Just<I>
+where
+    I: PartialEq,
+{
+    Just(item)
+}
+
+pub fn one_of<T, I>(items: T) -> OneOf<I>
+where
+    T: IntoIterator<Item = I>,
+    I: PartialEq,
+{
+    OneOf(items.into_iter().collect())
+}
+
+pub fn none_of<T, I>(items: T) -> NoneOf<I>
+where
+    T: IntoIterator<Item = I>,
+    I: PartialEq,
+{
+    NoneOf(items.into_iter().collect())
+}
+
+pub fn satisfy<F, I>(predicate: F) -> Satisfy<F>
+where
+    F: FnMut(&I) -> bool,
+{
+    Satisfy(predicate)
+}
+
+pub fn range<R, I>(range: R) -> Range<I>
+where
+    R: RangeBounds<I>,
+    I: PartialOrd + Clone,
+{
+    Range {
+        start: range.start_bound().cloned(),
+        end: range.end_bound().cloned(),
+    }
+}
+
 pub fn character(from: char) -> Char {
     Char(from)
 }
@@ -199,7 +438,7 @@ pub fn digit_range<R>(range: R) -> Option<impl Processor<char, Output = u8>>
 where
     R: RangeBounds<u8>,
 {
-    const ZERO: u8 = '0' as u8;
+    const ZERO: u8 = b'0';
     let start = digit_inclusive_or(range.start_bound(), 0);
     let end = digit_inclusive_or(range.end_bound(), 9);
     if start > end || start > 8 || end > 9 {
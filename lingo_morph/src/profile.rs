@@ -0,0 +1,127 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::trace::{Event, Outcome, Tracer};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub calls: u64,
+    pub done: u64,
+    pub mismatches: u64,
+    pub rollbacks: u64,
+    // Items from where a processor started to where it left off, so after any rollbacks
+    pub consumed: u64,
+    pub time: Duration,
+    // Time not spent in the processors it called
+    pub self_time: Duration,
+}
+
+struct Running {
+    name: &'static str,
+    start: usize,
+    started: Instant,
+    nested: Duration,
+}
+
+// Processors are told apart by name, so unnamed ones add up under their combinator's name
+#[derive(Default)]
+pub struct Profiler {
+    running: Vec<Running>,
+    stats: HashMap<&'static str, Stats>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn stats(&self, name: &str) -> Option<&Stats> {
+        self.stats.get(name)
+    }
+
+    // Slowest first, time spent in a processor itself is what points to where to look
+    pub fn ranked(&self) -> Vec<(&'static str, Stats)> {
+        let mut ranked = self
+            .stats
+            .iter()
+            .map(|(name, stats)| (*name, *stats))
+            .collect::<Vec<_>>();
+        ranked.sort_by(|a, b| b.1.self_time.cmp(&a.1.self_time).then(a.0.cmp(b.0)));
+        ranked
+    }
+
+    pub fn report(&self, top: usize) -> String {
+        let ranked = self.ranked();
+        let ranked = &ranked[..top.min(ranked.len())];
+        let width = ranked
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(["name".len()])
+            .max()
+            .unwrap_or_default();
+        let mut report = format!(
+            "{:<width$} {:>10} {:>10} {:>10} {:>10} {:>10} {:>12} {:>12}\n",
+            "name", "calls", "done", "mismatches", "rollbacks", "consumed", "time", "self time"
+        );
+        for (name, stats) in ranked {
+            report.push_str(&format!(
+                "{:<width$} {:>10} {:>10} {:>10} {:>10} {:>10} {:>12} {:>12}\n",
+                name,
+                stats.calls,
+                stats.done,
+                stats.mismatches,
+                stats.rollbacks,
+                stats.consumed,
+                format!("{:?}", stats.time),
+                format!("{:?}", stats.self_time),
+            ));
+        }
+        report
+    }
+}
+
+impl Tracer for Profiler {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Enter { name, offset } => self.running.push(Running {
+                name,
+                start: offset,
+                started: Instant::now(),
+                nested: Duration::ZERO,
+            }),
+            Event::Exit {
+                offset, outcome, ..
+            } => {
+                let Some(running) = self.running.pop() else {
+                    return;
+                };
+                let time = running.started.elapsed();
+                if let Some(parent) = self.running.last_mut() {
+                    parent.nested += time;
+                }
+                let stats = self.stats.entry(running.name).or_default();
+                stats.calls += 1;
+                match outcome {
+                    Outcome::Done => stats.done += 1,
+                    Outcome::Mismatch => stats.mismatches += 1,
+                    Outcome::Incomplete | Outcome::Failed => {}
+                }
+                let end = offset.unwrap_or(running.start);
+                stats.consumed += end.saturating_sub(running.start) as u64;
+                // Nested under itself the time is already part of the outer call
+                if self.running.iter().all(|x| x.name != running.name) {
+                    stats.time += time;
+                }
+                stats.self_time += time.saturating_sub(running.nested);
+            }
+            Event::Rollback { .. } => {
+                if let Some(running) = self.running.last() {
+                    self.stats.entry(running.name).or_default().rollbacks += 1;
+                }
+            }
+            Event::Taken { .. } => {}
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{
+    context::{cancelled, offset, Contextual},
+    processed,
+    source::{Source, Span},
+    Processor, Status,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Match<O> {
+    pub output: O,
+    pub span: Span,
+}
+
+pub struct FindIter<'a, 's, S, P> {
+    source: Option<Contextual<'s, S>>,
+    processor: &'a mut P,
+    overlapping: bool,
+    last_end: Option<usize>,
+    ended: bool,
+}
+
+impl<'a, 's, S, P> FindIter<'a, 's, S, P> {
+    pub(crate) fn new(source: Contextual<'s, S>, processor: &'a mut P, overlapping: bool) -> Self {
+        Self {
+            source: Some(source),
+            processor,
+            overlapping,
+            last_end: None,
+            ended: false,
+        }
+    }
+}
+
+impl<S, P, I> FindIter<'_, '_, S, P>
+where
+    P: Processor<I>,
+    S: Source<Item = I>,
+{
+    fn find(&mut self) -> Result<Option<Match<P::Output>>, processed::Error> {
+        loop {
+            let Some(mut source) = self.source.take() else {
+                return Ok(None);
+            };
+            if source.peek().is_none() {
+                if let Some(error) = source.take_error() {
+                    return Err(error);
+                }
+                // The end of input is tried once more for an empty match
+                if self.ended {
+                    return Ok(None);
+                }
+                self.ended = true;
+            }
+            cancelled(&mut source)?;
+            let fallback = source.snapshot();
+            let start = offset(&mut source);
+            match self.processor.process(source)? {
+                Status::Done(output, mut rest) => {
+                    let end = offset(&mut rest);
+                    if self.overlapping || start == end {
+                        rest.restore(fallback)?;
+                        rest.next();
+                    }
+                    self.source = Some(rest);
+                    // Like regex, an empty match right after the previous match is skipped
+                    if start == end && self.last_end == Some(start) {
+                        continue;
+                    }
+                    self.last_end = Some(end);
+                    let span = Span::new(start, end);
+                    return Ok(Some(Match { output, span }));
+                }
+                Status::Mismatch(mut rest) => {
+                    rest.restore(fallback)?;
+                    rest.next();
+                    self.source = Some(rest);
+                }
+                Status::Incomplete(needed) => return Err(needed.into()),
+            }
+        }
+    }
+}
+
+impl<S, P, I> Iterator for FindIter<'_, '_, S, P>
+where
+    P: Processor<I>,
+    S: Source<Item = I>,
+{
+    type Item = Result<Match<P::Output>, processed::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.find().transpose()
+    }
+}
+
+pub struct Matches<'a, 's, S, P>(FindIter<'a, 's, S, P>);
+
+impl<'a, 's, S, P> Matches<'a, 's, S, P> {
+    pub(crate) fn new(find: FindIter<'a, 's, S, P>) -> Self {
+        Self(find)
+    }
+}
+
+impl<S, P, I> Iterator for Matches<'_, '_, S, P>
+where
+    P: Processor<I>,
+    S: Source<Item = I>,
+{
+    type Item = Result<P::Output, processed::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.0.next()?.map(|found| found.output))
+    }
+}
@@ -1,4 +1,14 @@
-use std::{convert::Infallible, error::Error};
+use std::{
+    any::Any,
+    collections::{BTreeMap, VecDeque},
+    convert::Infallible,
+    error::Error,
+    fmt::{self, Display},
+    ops::Range,
+    rc::Rc,
+};
+
+use crate::{context::Context, processed};
 
 pub trait Source: Sized {
     type Item;
@@ -15,6 +25,51 @@ pub trait Source: Sized {
 
     fn peek_mut(&mut self) -> Option<&mut Self::Item>;
 
+    #[inline]
+    fn context(&mut self) -> Option<&mut Context> {
+        None
+    }
+
+    #[inline]
+    fn state(&mut self) -> Option<&mut dyn Any> {
+        None
+    }
+
+    #[inline]
+    fn position(&self) -> Option<Position> {
+        None
+    }
+
+    // Partial sources may get more items later, running out of them is not the end of the input
+    #[inline]
+    fn is_partial(&self) -> bool {
+        false
+    }
+
+    // Sources which can fail outside of a processor, such as a tokenizer, hand their error over here
+    #[inline]
+    fn take_error(&mut self) -> Option<processed::Error> {
+        None
+    }
+
+    // Sources over contiguous memory can hand out the items left all at once
+    #[inline]
+    fn as_slice(&self) -> Option<&[Self::Item]> {
+        None
+    }
+
+    // Moves past the next items at once when they're found next to each other in memory
+    #[inline]
+    fn take_slice(&mut self, _amount: usize) -> Option<&[Self::Item]> {
+        None
+    }
+
+    // Rolls back after looking ahead, which unlike a processor backtracking isn't counted or traced
+    #[inline]
+    fn restore(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        self.roll_back(to)
+    }
+
     #[inline]
     fn iter(&mut self) -> Iter<'_, Self> {
         Iter(self)
@@ -55,6 +110,81 @@ where
     }
 }
 
+// Processing a borrowed source leaves it with the caller, even when the processor fails
+impl<S> Source for &mut S
+where
+    S: Source,
+{
+    type Item = S::Item;
+    type Snapshot = S::Snapshot;
+    type RollBackErr = S::RollBackErr;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        (**self).next()
+    }
+
+    #[inline]
+    fn snapshot(&self) -> Self::Snapshot {
+        (**self).snapshot()
+    }
+
+    #[inline]
+    fn roll_back(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        (**self).roll_back(to)
+    }
+
+    #[inline]
+    fn peek(&mut self) -> Option<&Self::Item> {
+        (**self).peek()
+    }
+
+    #[inline]
+    fn peek_mut(&mut self) -> Option<&mut Self::Item> {
+        (**self).peek_mut()
+    }
+
+    #[inline]
+    fn context(&mut self) -> Option<&mut Context> {
+        (**self).context()
+    }
+
+    #[inline]
+    fn state(&mut self) -> Option<&mut dyn Any> {
+        (**self).state()
+    }
+
+    #[inline]
+    fn position(&self) -> Option<Position> {
+        (**self).position()
+    }
+
+    #[inline]
+    fn is_partial(&self) -> bool {
+        (**self).is_partial()
+    }
+
+    #[inline]
+    fn take_error(&mut self) -> Option<processed::Error> {
+        (**self).take_error()
+    }
+
+    #[inline]
+    fn as_slice(&self) -> Option<&[Self::Item]> {
+        (**self).as_slice()
+    }
+
+    #[inline]
+    fn take_slice(&mut self, amount: usize) -> Option<&[Self::Item]> {
+        (**self).take_slice(amount)
+    }
+
+    #[inline]
+    fn restore(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        (**self).restore(to)
+    }
+}
+
 pub struct BoxedSlice<T> {
     data: Box<[T]>,
     idx: usize,
@@ -82,13 +212,7 @@ where
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        self.data
-            .get(self.idx)
-            .map(|x| {
-                self.idx += 1;
-                x
-            })
-            .cloned()
+        self.data.get(self.idx).inspect(|_| self.idx += 1).cloned()
     }
 
     #[inline]
@@ -111,4 +235,659 @@ where
     fn peek_mut(&mut self) -> Option<&mut Self::Item> {
         self.data.get_mut(self.idx)
     }
+
+    #[inline]
+    fn as_slice(&self) -> Option<&[Self::Item]> {
+        Some(&self.data[self.idx..])
+    }
+
+    #[inline]
+    fn take_slice(&mut self, amount: usize) -> Option<&[Self::Item]> {
+        let taken = self.data.get(self.idx..self.idx + amount)?;
+        self.idx += amount;
+        Some(taken)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub const START: Self = Self {
+        offset: 0,
+        line: 1,
+        column: 1,
+    };
+
+    #[inline]
+    pub fn at_line_start(&self) -> bool {
+        self.column == 1
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::START
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+pub struct Located<S> {
+    source: S,
+    position: Position,
+}
+
+impl<S> Located<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            position: Position::START,
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+
+    // For a source that is a piece of a larger input, so positions count from the start of that
+    pub(crate) fn starting_at(mut self, position: Position) -> Self {
+        self.position = position;
+        self
+    }
+}
+
+impl<S> Source for Located<S>
+where
+    S: Source<Item = char>,
+{
+    type Item = char;
+    type Snapshot = (S::Snapshot, Position);
+    type RollBackErr = S::RollBackErr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.source.next()?;
+        self.position.offset += 1;
+        if next == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(next)
+    }
+
+    #[inline]
+    fn snapshot(&self) -> Self::Snapshot {
+        (self.source.snapshot(), self.position)
+    }
+
+    #[inline]
+    fn roll_back(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        self.source.roll_back(to.0)?;
+        self.position = to.1;
+        Ok(())
+    }
+
+    #[inline]
+    fn restore(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        self.source.restore(to.0)?;
+        self.position = to.1;
+        Ok(())
+    }
+
+    #[inline]
+    fn peek(&mut self) -> Option<&Self::Item> {
+        self.source.peek()
+    }
+
+    #[inline]
+    fn peek_mut(&mut self) -> Option<&mut Self::Item> {
+        self.source.peek_mut()
+    }
+
+    #[inline]
+    fn context(&mut self) -> Option<&mut Context> {
+        self.source.context()
+    }
+
+    #[inline]
+    fn state(&mut self) -> Option<&mut dyn Any> {
+        self.source.state()
+    }
+
+    #[inline]
+    fn position(&self) -> Option<Position> {
+        Some(self.position)
+    }
+
+    #[inline]
+    fn is_partial(&self) -> bool {
+        self.source.is_partial()
+    }
+
+    #[inline]
+    fn take_error(&mut self) -> Option<processed::Error> {
+        self.source.take_error()
+    }
+
+    #[inline]
+    fn as_slice(&self) -> Option<&[Self::Item]> {
+        self.source.as_slice()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    #[inline]
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    #[inline]
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+}
+
+impl From<Range<usize>> for Span {
+    fn from(value: Range<usize>) -> Self {
+        Self::new(value.start, value.end)
+    }
+}
+
+impl From<Span> for Range<usize> {
+    fn from(value: Span) -> Self {
+        value.start..value.end
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+pub struct Borrowed<'a, T> {
+    data: &'a [T],
+    idx: usize,
+    // Borrowed data can't be written to, so items changed through peek_mut are kept aside
+    edits: BTreeMap<usize, T>,
+}
+
+impl<'a, T> From<&'a [T]> for Borrowed<'a, T> {
+    fn from(value: &'a [T]) -> Self {
+        Self {
+            data: value,
+            idx: 0,
+            edits: BTreeMap::new(),
+        }
+    }
+}
+
+impl<'a, T> Borrowed<'a, T> {
+    // As borrowed, so without the items changed through peek_mut
+    #[inline]
+    pub fn remaining(&self) -> &'a [T] {
+        &self.data[self.idx..]
+    }
+
+    // Lent for as long as the input lives, so only while none of the items were changed through
+    // peek_mut
+    pub fn take_slice(&mut self, amount: usize) -> Option<&'a [T]> {
+        let range = self.idx..self.idx + amount;
+        if self.edits.range(range.clone()).next().is_some() {
+            return None;
+        }
+        let taken = self.data.get(range)?;
+        self.idx += amount;
+        Some(taken)
+    }
+}
+
+impl<T> Source for Borrowed<'_, T>
+where
+    T: Clone,
+{
+    type Item = T;
+    type Snapshot = usize;
+    type RollBackErr = Infallible;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.peek()?.clone();
+        self.idx += 1;
+        Some(next)
+    }
+
+    #[inline]
+    fn snapshot(&self) -> Self::Snapshot {
+        self.idx
+    }
+
+    #[inline]
+    fn roll_back(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        self.idx = to;
+        Ok(())
+    }
+
+    #[inline]
+    fn peek(&mut self) -> Option<&Self::Item> {
+        match self.edits.get(&self.idx) {
+            Some(edited) => Some(edited),
+            None => self.data.get(self.idx),
+        }
+    }
+
+    // Changes stay when rolling back, as they do for data which is owned
+    fn peek_mut(&mut self) -> Option<&mut Self::Item> {
+        let current = self.data.get(self.idx)?.clone();
+        Some(self.edits.entry(self.idx).or_insert(current))
+    }
+
+    #[inline]
+    fn as_slice(&self) -> Option<&[Self::Item]> {
+        match self.edits.range(self.idx..).next() {
+            Some(_) => None,
+            None => Some(self.remaining()),
+        }
+    }
+
+    #[inline]
+    fn take_slice(&mut self, amount: usize) -> Option<&[Self::Item]> {
+        Borrowed::take_slice(self, amount)
+    }
+}
+
+pub struct Limit<S> {
+    source: S,
+    remaining: usize,
+}
+
+impl<S> Limit<S> {
+    pub fn new(source: S, limit: usize) -> Self {
+        Self {
+            source,
+            remaining: limit,
+        }
+    }
+
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S> Source for Limit<S>
+where
+    S: Source,
+{
+    type Item = S::Item;
+    type Snapshot = (S::Snapshot, usize);
+    type RollBackErr = S::RollBackErr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let next = self.source.next()?;
+        self.remaining -= 1;
+        Some(next)
+    }
+
+    #[inline]
+    fn snapshot(&self) -> Self::Snapshot {
+        (self.source.snapshot(), self.remaining)
+    }
+
+    #[inline]
+    fn roll_back(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        self.source.roll_back(to.0)?;
+        self.remaining = to.1;
+        Ok(())
+    }
+
+    #[inline]
+    fn restore(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        self.source.restore(to.0)?;
+        self.remaining = to.1;
+        Ok(())
+    }
+
+    #[inline]
+    fn peek(&mut self) -> Option<&Self::Item> {
+        if self.remaining == 0 {
+            None
+        } else {
+            self.source.peek()
+        }
+    }
+
+    #[inline]
+    fn peek_mut(&mut self) -> Option<&mut Self::Item> {
+        if self.remaining == 0 {
+            None
+        } else {
+            self.source.peek_mut()
+        }
+    }
+
+    #[inline]
+    fn context(&mut self) -> Option<&mut Context> {
+        self.source.context()
+    }
+
+    #[inline]
+    fn state(&mut self) -> Option<&mut dyn Any> {
+        self.source.state()
+    }
+
+    #[inline]
+    fn position(&self) -> Option<Position> {
+        self.source.position()
+    }
+
+    #[inline]
+    fn is_partial(&self) -> bool {
+        self.remaining > 0 && self.source.is_partial()
+    }
+
+    #[inline]
+    fn take_error(&mut self) -> Option<processed::Error> {
+        self.source.take_error()
+    }
+
+    #[inline]
+    fn as_slice(&self) -> Option<&[Self::Item]> {
+        let left = self.source.as_slice()?;
+        Some(&left[..left.len().min(self.remaining)])
+    }
+
+    fn take_slice(&mut self, amount: usize) -> Option<&[Self::Item]> {
+        if amount > self.remaining {
+            return None;
+        }
+        let taken = self.source.take_slice(amount)?;
+        self.remaining -= amount;
+        Some(taken)
+    }
+}
+
+// Marks the wrapped source as possibly getting more items, until finish is called
+pub struct Partial<S> {
+    source: S,
+    partial: bool,
+}
+
+impl<S> Partial<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            partial: true,
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.partial = false;
+    }
+
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S> Source for Partial<S>
+where
+    S: Source,
+{
+    type Item = S::Item;
+    type Snapshot = S::Snapshot;
+    type RollBackErr = S::RollBackErr;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.source.next()
+    }
+
+    #[inline]
+    fn snapshot(&self) -> Self::Snapshot {
+        self.source.snapshot()
+    }
+
+    #[inline]
+    fn roll_back(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        self.source.roll_back(to)
+    }
+
+    #[inline]
+    fn restore(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        self.source.restore(to)
+    }
+
+    #[inline]
+    fn peek(&mut self) -> Option<&Self::Item> {
+        self.source.peek()
+    }
+
+    #[inline]
+    fn peek_mut(&mut self) -> Option<&mut Self::Item> {
+        self.source.peek_mut()
+    }
+
+    #[inline]
+    fn context(&mut self) -> Option<&mut Context> {
+        self.source.context()
+    }
+
+    #[inline]
+    fn state(&mut self) -> Option<&mut dyn Any> {
+        self.source.state()
+    }
+
+    #[inline]
+    fn position(&self) -> Option<Position> {
+        self.source.position()
+    }
+
+    #[inline]
+    fn is_partial(&self) -> bool {
+        self.partial
+    }
+
+    #[inline]
+    fn take_error(&mut self) -> Option<processed::Error> {
+        self.source.take_error()
+    }
+
+    #[inline]
+    fn as_slice(&self) -> Option<&[Self::Item]> {
+        self.source.as_slice()
+    }
+
+    #[inline]
+    fn take_slice(&mut self, amount: usize) -> Option<&[Self::Item]> {
+        self.source.take_slice(amount)
+    }
+}
+
+// Buffers pulled items only while a snapshot could still roll back to them
+pub struct IterSource<I>
+where
+    I: Iterator,
+{
+    iter: I,
+    buffer: VecDeque<I::Item>,
+    base: usize,
+    idx: usize,
+    alive: Rc<()>,
+    partial: bool,
+}
+
+impl<I> IterSource<I>
+where
+    I: Iterator,
+{
+    pub fn new<T>(iter: T) -> Self
+    where
+        T: IntoIterator<IntoIter = I>,
+    {
+        Self {
+            iter: iter.into_iter(),
+            buffer: VecDeque::new(),
+            base: 0,
+            idx: 0,
+            alive: Rc::new(()),
+            partial: false,
+        }
+    }
+
+    // An iterator which ran out may still yield items later on, such as one over a channel
+    pub fn partial(mut self, partial: bool) -> Self {
+        self.partial = partial;
+        self
+    }
+
+    #[inline]
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    #[inline]
+    fn snapshots_alive(&self) -> bool {
+        Rc::strong_count(&self.alive) > 1
+    }
+
+    fn compact(&mut self) {
+        if !self.snapshots_alive() {
+            self.buffer.drain(..self.idx - self.base);
+            self.base = self.idx;
+        }
+    }
+
+    fn fill(&mut self) -> Option<usize> {
+        self.compact();
+        let at = self.idx - self.base;
+        if at == self.buffer.len() {
+            self.buffer.push_back(self.iter.next()?);
+        }
+        Some(at)
+    }
+}
+
+impl<I> From<I> for IterSource<I>
+where
+    I: Iterator,
+{
+    fn from(value: I) -> Self {
+        Self::new(value)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IterSnapshot {
+    idx: usize,
+    _alive: Rc<()>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpiredSnapshot(pub(crate) usize);
+
+impl Display for ExpiredSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "item {} is no longer buffered", self.0)
+    }
+}
+
+impl Error for ExpiredSnapshot {}
+
+impl<I> Source for IterSource<I>
+where
+    I: Iterator,
+    I::Item: Clone,
+{
+    type Item = I::Item;
+    type Snapshot = IterSnapshot;
+    type RollBackErr = ExpiredSnapshot;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.compact();
+        let at = self.idx - self.base;
+        let next = if self.snapshots_alive() {
+            if at == self.buffer.len() {
+                self.buffer.push_back(self.iter.next()?);
+            }
+            self.buffer.get(at).cloned()
+        } else {
+            self.base += 1;
+            self.buffer.pop_front().or_else(|| self.iter.next())
+        };
+        match next {
+            Some(next) => {
+                self.idx += 1;
+                Some(next)
+            }
+            None => {
+                self.base = self.idx;
+                None
+            }
+        }
+    }
+
+    #[inline]
+    fn snapshot(&self) -> Self::Snapshot {
+        IterSnapshot {
+            idx: self.idx,
+            _alive: Rc::clone(&self.alive),
+        }
+    }
+
+    fn roll_back(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        if to.idx < self.base || to.idx > self.base + self.buffer.len() {
+            return Err(ExpiredSnapshot(to.idx));
+        }
+        self.idx = to.idx;
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Option<&Self::Item> {
+        let at = self.fill()?;
+        self.buffer.get(at)
+    }
+
+    fn peek_mut(&mut self) -> Option<&mut Self::Item> {
+        let at = self.fill()?;
+        self.buffer.get_mut(at)
+    }
+
+    #[inline]
+    fn is_partial(&self) -> bool {
+        self.partial
+    }
 }
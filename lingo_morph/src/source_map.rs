@@ -0,0 +1,235 @@
+use std::fmt::Write as _;
+
+use crate::source::{Position, Span};
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Offsets on both sides count chars, a copied mapping corresponds char by char
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Mapping {
+    pub input: Span,
+    pub output: Span,
+    pub replaced: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMap {
+    mappings: Vec<Mapping>,
+    input_lines: Vec<usize>,
+    output_lines: Vec<usize>,
+    // Offsets of chars taking two UTF-16 code units, which the JSON columns count twice
+    input_wide: Vec<usize>,
+    output_wide: Vec<usize>,
+}
+
+impl Default for SourceMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self {
+            mappings: Vec::new(),
+            input_lines: vec![0],
+            output_lines: vec![0],
+            input_wide: Vec::new(),
+            output_wide: Vec::new(),
+        }
+    }
+
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
+
+    // The input span that produced the given output offset
+    pub fn original(&self, offset: usize) -> Option<Span> {
+        let idx = self.mappings.partition_point(|x| x.output.end <= offset);
+        let mapping = self
+            .mappings
+            .get(idx)
+            .filter(|x| x.output.contains(offset))?;
+        Some(narrow(mapping, mapping.output, mapping.input, offset))
+    }
+
+    // The output span the given input offset ended up in
+    pub fn generated(&self, offset: usize) -> Option<Span> {
+        let idx = self.mappings.partition_point(|x| x.input.end <= offset);
+        let mapping = self
+            .mappings
+            .get(idx)
+            .filter(|x| x.input.contains(offset))?;
+        Some(narrow(mapping, mapping.input, mapping.output, offset))
+    }
+
+    pub fn original_position(&self, offset: usize) -> Option<Position> {
+        let span = self.original(offset)?;
+        Some(position(&self.input_lines, span.start))
+    }
+
+    pub fn generated_position(&self, offset: usize) -> Option<Position> {
+        let span = self.generated(offset)?;
+        Some(position(&self.output_lines, span.start))
+    }
+
+    // Source Map revision 3, which counts columns in UTF-16 code units instead of chars
+    pub fn to_json(&self, file: &str, source: &str) -> String {
+        let mut mappings = String::new();
+        let mut line = 0;
+        let mut column = 0;
+        let mut original = (0, 0);
+        for mapping in self.mappings.iter().filter(|x| !x.output.is_empty()) {
+            let generated = utf16(&self.output_lines, &self.output_wide, mapping.output.start);
+            let input = utf16(&self.input_lines, &self.input_wide, mapping.input.start);
+            if generated.0 > line {
+                mappings.extend((line..generated.0).map(|_| ';'));
+                line = generated.0;
+                column = 0;
+            } else if !mappings.is_empty() && !mappings.ends_with(';') {
+                mappings.push(',');
+            }
+            vlq(&mut mappings, generated.1 - column);
+            vlq(&mut mappings, 0);
+            vlq(&mut mappings, input.0 as i64 - original.0);
+            vlq(&mut mappings, input.1 - original.1);
+            column = generated.1;
+            original = (input.0 as i64, input.1);
+        }
+        let mut json = String::new();
+        json.push_str("{\"version\":3,\"file\":");
+        quote(&mut json, file);
+        json.push_str(",\"sources\":[");
+        quote(&mut json, source);
+        json.push_str("],\"names\":[],\"mappings\":");
+        quote(&mut json, &mappings);
+        json.push('}');
+        json
+    }
+
+    pub(crate) fn copy(&mut self, input: usize, output: usize, copied: char) {
+        let new_line = self.output_lines.last() == Some(&output);
+        match self.mappings.last_mut() {
+            Some(last)
+                if !last.replaced
+                    && !new_line
+                    && last.input.end == input
+                    && last.output.end == output =>
+            {
+                last.input.end += 1;
+                last.output.end += 1;
+            }
+            _ => self.mappings.push(Mapping {
+                input: Span::new(input, input + 1),
+                output: Span::new(output, output + 1),
+                replaced: false,
+            }),
+        }
+        if copied == '\n' {
+            self.input_lines.push(input + 1);
+            self.output_lines.push(output + 1);
+        }
+        if copied.len_utf16() > 1 {
+            self.input_wide.push(input);
+            self.output_wide.push(output);
+        }
+    }
+
+    pub(crate) fn replace(&mut self, input: Span, output: usize, matched: &str, replaced: &str) {
+        lines(
+            &mut self.input_lines,
+            &mut self.input_wide,
+            input.start,
+            matched,
+        );
+        let end = lines(
+            &mut self.output_lines,
+            &mut self.output_wide,
+            output,
+            replaced,
+        );
+        self.mappings.push(Mapping {
+            input,
+            output: Span::new(output, end),
+            replaced: true,
+        });
+    }
+}
+
+// Copied text maps one to one, a replacement only as a whole
+fn narrow(mapping: &Mapping, from: Span, to: Span, offset: usize) -> Span {
+    if mapping.replaced {
+        to
+    } else {
+        let at = to.start + offset - from.start;
+        Span::new(at, at + 1)
+    }
+}
+
+fn lines(starts: &mut Vec<usize>, wide: &mut Vec<usize>, start: usize, text: &str) -> usize {
+    let mut offset = start;
+    for next in text.chars() {
+        if next.len_utf16() > 1 {
+            wide.push(offset);
+        }
+        offset += 1;
+        if next == '\n' {
+            starts.push(offset);
+        }
+    }
+    offset
+}
+
+fn position(starts: &[usize], offset: usize) -> Position {
+    let line = starts.partition_point(|x| *x <= offset);
+    Position {
+        offset,
+        line,
+        column: offset - starts[line - 1] + 1,
+    }
+}
+
+// Zero based line and UTF-16 column of a char offset
+fn utf16(starts: &[usize], wide: &[usize], offset: usize) -> (usize, i64) {
+    let position = position(starts, offset);
+    let line_start = starts[position.line - 1];
+    let wide = wide.partition_point(|x| *x < offset) - wide.partition_point(|x| *x < line_start);
+    (position.line - 1, (position.column - 1 + wide) as i64)
+}
+
+fn vlq(into: &mut String, value: i64) {
+    let mut rest = if value < 0 {
+        (-value as u64) << 1 | 1
+    } else {
+        (value as u64) << 1
+    };
+    loop {
+        let mut digit = (rest & 0x1f) as usize;
+        rest >>= 5;
+        if rest > 0 {
+            digit |= 0x20;
+        }
+        into.push(char::from(BASE64[digit]));
+        if rest == 0 {
+            break;
+        }
+    }
+}
+
+pub(crate) fn quote(into: &mut String, text: &str) {
+    into.push('"');
+    for next in text.chars() {
+        match next {
+            '"' => into.push_str("\\\""),
+            '\\' => into.push_str("\\\\"),
+            '\n' => into.push_str("\\n"),
+            '\r' => into.push_str("\\r"),
+            '\t' => into.push_str("\\t"),
+            next if next.is_control() => {
+                let _ = write!(into, "\\u{:04x}", u32::from(next));
+            }
+            next => into.push(next),
+        }
+    }
+    into.push('"');
+}
@@ -0,0 +1,352 @@
+use std::{
+    cell::RefCell,
+    fmt::{self, Debug, Display},
+    rc::Rc,
+};
+
+use crate::source_map::quote;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Done,
+    Mismatch,
+    Incomplete,
+    Failed,
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome = match self {
+            Outcome::Done => "done",
+            Outcome::Mismatch => "mismatch",
+            Outcome::Incomplete => "incomplete",
+            Outcome::Failed => "failed",
+        };
+        write!(f, "{outcome}")
+    }
+}
+
+// Offsets count items from the start of the input, as the context does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Event {
+    Enter {
+        name: &'static str,
+        offset: usize,
+    },
+    // Once a processor is incomplete or failed there's no source left to tell the offset
+    Exit {
+        name: &'static str,
+        offset: Option<usize>,
+        outcome: Outcome,
+    },
+    Rollback {
+        from: usize,
+        to: usize,
+    },
+    // A choice tells which of its alternatives matched, counting from 1
+    Taken {
+        alternative: usize,
+    },
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Enter { name, offset } => write!(f, "enter {name} at {offset}"),
+            Event::Exit {
+                name,
+                offset: Some(offset),
+                outcome,
+            } => write!(f, "exit {name} at {offset}: {outcome}"),
+            Event::Exit { name, outcome, .. } => write!(f, "exit {name}: {outcome}"),
+            Event::Rollback { from, to } => write!(f, "rollback from {from} to {to}"),
+            Event::Taken { alternative } => write!(f, "took alternative {alternative}"),
+        }
+    }
+}
+
+pub trait Tracer {
+    fn event(&mut self, event: Event);
+}
+
+impl<F> Tracer for F
+where
+    F: FnMut(Event),
+{
+    fn event(&mut self, event: Event) {
+        self(event)
+    }
+}
+
+impl Debug for dyn Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Tracer")
+    }
+}
+
+pub(crate) type Shared = Rc<RefCell<dyn Tracer>>;
+
+pub(crate) fn emit(tracer: Option<&Shared>, event: Event) {
+    trace!("{event}");
+    if let Some(tracer) = tracer {
+        tracer.borrow_mut().event(event);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Attempt(Attempt),
+    Rollback { from: usize, to: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attempt {
+    name: &'static str,
+    start: usize,
+    end: Option<usize>,
+    outcome: Outcome,
+    taken: Option<usize>,
+    steps: Vec<Step>,
+}
+
+impl Attempt {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    // Not known for attempts which were incomplete or failed
+    pub fn end(&self) -> Option<usize> {
+        self.end
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    // Which alternative a choice matched with, counting from 1
+    pub fn taken(&self) -> Option<usize> {
+        self.taken
+    }
+
+    fn summary(&self) -> String {
+        let span = match self.end {
+            Some(end) if end != self.start => format!("{}..{}", self.start, end),
+            Some(_) => self.start.to_string(),
+            None => format!("{}..", self.start),
+        };
+        let mut summary = format!("{} {} {}", self.name, span, self.outcome);
+        if let Some(taken) = self.taken {
+            summary.push_str(&format!(", took alternative {taken}"));
+        }
+        summary
+    }
+}
+
+struct Pending {
+    name: &'static str,
+    start: usize,
+    taken: Option<usize>,
+    steps: Vec<Step>,
+}
+
+// Builds the tree of attempts out of the traced events
+#[derive(Default)]
+pub struct Recorder {
+    pending: Vec<Pending>,
+    steps: Vec<Step>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Attempts which haven't exited yet aren't part of it
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    fn push(&mut self, step: Step) {
+        match self.pending.last_mut() {
+            Some(parent) => parent.steps.push(step),
+            None => self.steps.push(step),
+        }
+    }
+
+    pub fn to_tree(&self) -> String {
+        let mut tree = String::new();
+        for step in &self.steps {
+            write_tree(&mut tree, step, 0);
+        }
+        tree
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        write_json_steps(&mut json, &self.steps);
+        json
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut html = String::from(HTML_HEAD);
+        for step in &self.steps {
+            write_html(&mut html, step);
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+impl Tracer for Recorder {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Enter { name, offset } => self.pending.push(Pending {
+                name,
+                start: offset,
+                taken: None,
+                steps: Vec::new(),
+            }),
+            Event::Exit {
+                offset, outcome, ..
+            } => {
+                let Some(pending) = self.pending.pop() else {
+                    return;
+                };
+                let attempt = Attempt {
+                    name: pending.name,
+                    start: pending.start,
+                    end: offset,
+                    outcome,
+                    taken: pending.taken,
+                    steps: pending.steps,
+                };
+                self.push(Step::Attempt(attempt));
+            }
+            Event::Rollback { from, to } => self.push(Step::Rollback { from, to }),
+            Event::Taken { alternative } => {
+                if let Some(pending) = self.pending.last_mut() {
+                    pending.taken = Some(alternative);
+                }
+            }
+        }
+    }
+}
+
+fn write_tree(into: &mut String, step: &Step, depth: usize) {
+    into.extend((0..depth).map(|_| "  "));
+    match step {
+        Step::Attempt(attempt) => {
+            into.push_str(&attempt.summary());
+            into.push('\n');
+            for step in &attempt.steps {
+                write_tree(into, step, depth + 1);
+            }
+        }
+        Step::Rollback { from, to } => into.push_str(&format!("rollback {from} -> {to}\n")),
+    }
+}
+
+fn write_json_steps(into: &mut String, steps: &[Step]) {
+    into.push('[');
+    for (idx, step) in steps.iter().enumerate() {
+        if idx > 0 {
+            into.push(',');
+        }
+        match step {
+            Step::Attempt(attempt) => {
+                into.push_str("{\"name\":");
+                quote(into, attempt.name);
+                into.push_str(&format!(",\"start\":{}", attempt.start));
+                match attempt.end {
+                    Some(end) => into.push_str(&format!(",\"end\":{end}")),
+                    None => into.push_str(",\"end\":null"),
+                }
+                into.push_str(&format!(",\"outcome\":\"{}\"", attempt.outcome));
+                if let Some(taken) = attempt.taken {
+                    into.push_str(&format!(",\"taken\":{taken}"));
+                }
+                into.push_str(",\"steps\":");
+                write_json_steps(into, &attempt.steps);
+                into.push('}');
+            }
+            Step::Rollback { from, to } => {
+                into.push_str(&format!("{{\"rollback\":{{\"from\":{from},\"to\":{to}}}}}"))
+            }
+        }
+    }
+    into.push(']');
+}
+
+const HTML_HEAD: &str = "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>Trace</title>
+<style>
+body { font-family: monospace; }
+details, div { margin-left: 1.5em; }
+.done { color: green; }
+.mismatch { color: gray; }
+.incomplete { color: orange; }
+.failed { color: red; }
+.rollback { color: purple; }
+</style>
+</head>
+<body>
+";
+
+fn write_html(into: &mut String, step: &Step) {
+    match step {
+        Step::Attempt(attempt) if attempt.steps.is_empty() => {
+            into.push_str(&format!(
+                "<div class=\"{}\">{}</div>\n",
+                attempt.outcome,
+                escape(&attempt.summary())
+            ));
+        }
+        Step::Attempt(attempt) => {
+            // Only what led to the result is unfolded
+            let open = if attempt.outcome == Outcome::Mismatch {
+                ""
+            } else {
+                " open"
+            };
+            into.push_str(&format!(
+                "<details{open}><summary class=\"{}\">{}</summary>\n",
+                attempt.outcome,
+                escape(&attempt.summary())
+            ));
+            for step in &attempt.steps {
+                write_html(into, step);
+            }
+            into.push_str("</details>\n");
+        }
+        Step::Rollback { from, to } => {
+            into.push_str(&format!(
+                "<div class=\"rollback\">rollback {from} -&gt; {to}</div>\n"
+            ));
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for next in text.chars() {
+        match next {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            next => escaped.push(next),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,372 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    rc::Rc,
+};
+
+use crate::{
+    context::guarded,
+    done,
+    grammar::{Describe, Expr, Grammar},
+    mismatch,
+    processed::{self, incomplete, Needed},
+    source::{Position, Source},
+    try_done, Processed, Processor, Status,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TriviaKind {
+    Whitespace,
+    LineComment,
+    BlockComment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TriviaPiece {
+    pub kind: TriviaKind,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Trivia {
+    whitespace: fn(&char) -> bool,
+    line_comments: Vec<String>,
+    block_comments: Vec<(String, String)>,
+    nested: bool,
+}
+
+impl Trivia {
+    pub fn new() -> Self {
+        Self {
+            whitespace: char::is_ascii_whitespace,
+            line_comments: Vec::new(),
+            block_comments: Vec::new(),
+            nested: false,
+        }
+    }
+
+    pub fn whitespace(mut self, class: fn(&char) -> bool) -> Self {
+        self.whitespace = class;
+        self
+    }
+
+    pub fn line_comment<T>(mut self, start: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.line_comments.push(start.into());
+        self
+    }
+
+    pub fn block_comment<T, U>(mut self, start: T, end: U) -> Self
+    where
+        T: Into<String>,
+        U: Into<String>,
+    {
+        self.block_comments.push((start.into(), end.into()));
+        self
+    }
+
+    pub fn nested(mut self, nested: bool) -> Self {
+        self.nested = nested;
+        self
+    }
+
+    // False when a partial source ran out before the trivia was known to end
+    pub fn skip<S>(&self, given: &mut S) -> Result<bool, processed::Error>
+    where
+        S: Source<Item = char>,
+    {
+        self.consume(given, &mut |_| ())
+    }
+
+    // None when a partial source ran out before the trivia was known to end
+    pub fn capture<S>(&self, given: &mut S) -> Result<Option<Vec<TriviaPiece>>, processed::Error>
+    where
+        S: Source<Item = char>,
+    {
+        let mut captured = Vec::new();
+        let ended = self.consume(given, &mut |piece| captured.push(piece))?;
+        Ok(ended.then_some(captured))
+    }
+
+    fn consume<S, F>(&self, given: &mut S, found: &mut F) -> Result<bool, processed::Error>
+    where
+        S: Source<Item = char>,
+        F: FnMut(TriviaPiece),
+    {
+        'pieces: loop {
+            // More trivia or the start of a comment may still arrive
+            if given.is_partial() && given.peek().is_none() {
+                return Ok(false);
+            }
+            let mut text = String::new();
+            while let Some(next) = given.next_if(self.whitespace) {
+                text.push(next);
+            }
+            if !text.is_empty() {
+                found(TriviaPiece {
+                    kind: TriviaKind::Whitespace,
+                    text,
+                });
+                continue;
+            }
+            for start in self.line_comments.iter() {
+                let Some(eaten) = eat(given, start, &mut text)? else {
+                    return Ok(false);
+                };
+                if eaten {
+                    while let Some(next) = given.next_if(|x| *x != '\n') {
+                        text.push(next);
+                    }
+                    found(TriviaPiece {
+                        kind: TriviaKind::LineComment,
+                        text,
+                    });
+                    continue 'pieces;
+                }
+            }
+            for (start, end) in self.block_comments.iter() {
+                let position = given.position();
+                let Some(eaten) = eat(given, start, &mut text)? else {
+                    return Ok(false);
+                };
+                if eaten {
+                    let mut depth = 1;
+                    while depth > 0 {
+                        let Some(ended) = eat(given, end, &mut text)? else {
+                            return Ok(false);
+                        };
+                        if ended {
+                            depth -= 1;
+                            continue;
+                        }
+                        if self.nested {
+                            let Some(nested) = eat(given, start, &mut text)? else {
+                                return Ok(false);
+                            };
+                            if nested {
+                                depth += 1;
+                                continue;
+                            }
+                        }
+                        match given.next() {
+                            Some(next) => text.push(next),
+                            None if given.is_partial() => return Ok(false),
+                            None => return Err(UnterminatedComment(position).into()),
+                        }
+                    }
+                    found(TriviaPiece {
+                        kind: TriviaKind::BlockComment,
+                        text,
+                    });
+                    continue 'pieces;
+                }
+            }
+            return Ok(true);
+        }
+    }
+}
+
+impl Default for Trivia {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnterminatedComment(Option<Position>);
+
+impl Display for UnterminatedComment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unterminated block comment")?;
+        match self.0 {
+            Some(position) => write!(f, " starting at {position}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Error for UnterminatedComment {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Lexeme<P>(P);
+
+impl<P> Processor<char> for Lexeme<P>
+where
+    P: Processor<char>,
+{
+    type Output = P::Output;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        guarded("lexeme", given, |given| {
+            let (output, mut rest) = try_done!(self.0.process(given));
+            if !configured(&mut rest).skip(&mut rest)? {
+                return incomplete(Needed::Unknown);
+            }
+            done(output, rest)
+        })
+    }
+}
+
+impl<P> Describe for Lexeme<P>
+where
+    P: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        let lexeme = self.0.describe(grammar);
+        lexeme.then(Expr::special("trivia"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CapturedLexeme<P>(P);
+
+impl<P> Processor<char> for CapturedLexeme<P>
+where
+    P: Processor<char>,
+{
+    type Output = (P::Output, Vec<TriviaPiece>);
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        guarded("lexeme_captured", given, |given| {
+            let (output, mut rest) = try_done!(self.0.process(given));
+            match configured(&mut rest).capture(&mut rest)? {
+                Some(trivia) => done((output, trivia), rest),
+                None => incomplete(Needed::Unknown),
+            }
+        })
+    }
+}
+
+impl<P> Describe for CapturedLexeme<P>
+where
+    P: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        let lexeme = self.0.describe(grammar);
+        lexeme.then(Expr::special("trivia"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Padded<P>(P);
+
+impl<P> Processor<char> for Padded<P>
+where
+    P: Processor<char>,
+{
+    type Output = P::Output;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        guarded("padded", given, |mut given| {
+            let trivia = configured(&mut given);
+            let fallback = given.snapshot();
+            if !trivia.skip(&mut given)? {
+                return incomplete(Needed::Unknown);
+            }
+            match self.0.process(given)? {
+                Status::Done(output, mut rest) => match trivia.skip(&mut rest)? {
+                    true => done(output, rest),
+                    false => incomplete(Needed::Unknown),
+                },
+                Status::Mismatch(mut rest) => {
+                    rest.roll_back(fallback)?;
+                    mismatch(rest)
+                }
+                Status::Incomplete(needed) => incomplete(needed),
+            }
+        })
+    }
+}
+
+impl<P> Describe for Padded<P>
+where
+    P: Describe,
+{
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        let padded = Expr::special("trivia").then(self.0.describe(grammar));
+        padded.then(Expr::special("trivia"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Captured;
+
+impl Processor<char> for Captured {
+    type Output = Vec<TriviaPiece>;
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        match configured(&mut given).capture(&mut given)? {
+            Some(trivia) => done(trivia, given),
+            None => incomplete(Needed::Unknown),
+        }
+    }
+}
+
+impl Describe for Captured {
+    fn describe(&self, _: &mut Grammar) -> Expr {
+        Expr::special("trivia")
+    }
+}
+
+pub fn trivia() -> Captured {
+    Captured
+}
+
+pub(crate) fn lexeme<P>(processor: P) -> Lexeme<P> {
+    Lexeme(processor)
+}
+
+pub(crate) fn lexeme_captured<P>(processor: P) -> CapturedLexeme<P> {
+    CapturedLexeme(processor)
+}
+
+pub(crate) fn padded<P>(processor: P) -> Padded<P> {
+    Padded(processor)
+}
+
+fn configured<S>(given: &mut S) -> Rc<Trivia>
+where
+    S: Source,
+{
+    given
+        .context()
+        .and_then(|context| context.trivia())
+        .unwrap_or_default()
+}
+
+// None when a partial source ran out while the text still matched
+fn eat<S>(given: &mut S, expected: &str, text: &mut String) -> Result<Option<bool>, S::RollBackErr>
+where
+    S: Source<Item = char>,
+{
+    let mut chars = expected.chars();
+    match chars.next() {
+        Some(first) if given.peek() == Some(&first) => (),
+        _ => return Ok(Some(false)),
+    }
+    let fallback = given.snapshot();
+    given.next();
+    for expected in chars {
+        if given.next_if_eq(&expected).is_none() {
+            let ran_out = given.is_partial() && given.peek().is_none();
+            given.restore(fallback)?;
+            return Ok((!ran_out).then_some(false));
+        }
+    }
+    text.push_str(expected);
+    Ok(Some(true))
+}
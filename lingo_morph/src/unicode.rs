@@ -0,0 +1,418 @@
+use std::{any::Any, collections::BTreeMap, convert::Infallible};
+
+use unicode_normalization::{
+    char::{canonical_combining_class, compose},
+    UnicodeNormalization,
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    context::Context,
+    processed,
+    source::{Position, Source},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Form {
+    Nfc,
+    Nfd,
+    Nfkc,
+    Nfkd,
+}
+
+pub fn normalize(text: &str, form: Form) -> String {
+    match form {
+        Form::Nfc => text.nfc().collect(),
+        Form::Nfd => text.nfd().collect(),
+        Form::Nfkc => text.nfkc().collect(),
+        Form::Nfkd => text.nfkd().collect(),
+    }
+}
+
+// Yields extended grapheme clusters, the position counts clusters rather than chars
+pub struct Graphemes<'a> {
+    text: &'a str,
+    idx: usize,
+    position: Position,
+    peeked: &'a str,
+    // Borrowed text can't be written to, so clusters changed through peek_mut are kept aside
+    edits: BTreeMap<usize, &'a str>,
+    partial: bool,
+}
+
+impl<'a> From<&'a str> for Graphemes<'a> {
+    fn from(value: &'a str) -> Self {
+        Self {
+            text: value,
+            idx: 0,
+            position: Position::START,
+            peeked: "",
+            edits: BTreeMap::new(),
+            partial: false,
+        }
+    }
+}
+
+impl<'a> Graphemes<'a> {
+    #[inline]
+    pub fn remaining(&self) -> &'a str {
+        &self.text[self.idx..]
+    }
+
+    // The text is only the start of the input, so its last cluster isn't handed out
+    pub fn partial(mut self, partial: bool) -> Self {
+        self.partial = partial;
+        self
+    }
+
+    fn cluster(&self) -> Option<&'a str> {
+        let mut clusters = self.remaining().graphemes(true);
+        let cluster = clusters.next()?;
+        if self.partial && clusters.next().is_none() {
+            return None;
+        }
+        Some(cluster)
+    }
+}
+
+impl<'a> Source for Graphemes<'a> {
+    type Item = &'a str;
+    type Snapshot = (usize, Position);
+    type RollBackErr = Infallible;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.cluster()?;
+        let edited = self.edits.get(&self.idx).copied();
+        self.idx += next.len();
+        self.position.offset += 1;
+        if next == "\n" || next == "\r\n" {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(edited.unwrap_or(next))
+    }
+
+    #[inline]
+    fn snapshot(&self) -> Self::Snapshot {
+        (self.idx, self.position)
+    }
+
+    #[inline]
+    fn roll_back(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        (self.idx, self.position) = to;
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Option<&Self::Item> {
+        let cluster = self.cluster()?;
+        self.peeked = self.edits.get(&self.idx).copied().unwrap_or(cluster);
+        Some(&self.peeked)
+    }
+
+    // Changes stay when rolling back, as they do for data which is owned
+    fn peek_mut(&mut self) -> Option<&mut Self::Item> {
+        let cluster = self.cluster()?;
+        Some(self.edits.entry(self.idx).or_insert(cluster))
+    }
+
+    #[inline]
+    fn position(&self) -> Option<Position> {
+        Some(self.position)
+    }
+
+    #[inline]
+    fn is_partial(&self) -> bool {
+        self.partial
+    }
+}
+
+// Normalizes the chars of any source as they're read, one segment of a starter and the marks
+// following it at a time
+pub struct Normalized<S> {
+    source: S,
+    form: Form,
+    segment: Vec<char>,
+    idx: usize,
+    error: Option<processed::Error>,
+}
+
+impl<S> Normalized<S> {
+    pub fn new(source: S, form: Form) -> Self {
+        Self {
+            source,
+            form,
+            segment: Vec::new(),
+            idx: 0,
+            error: None,
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S> Normalized<S>
+where
+    S: Source<Item = char>,
+{
+    // Whether the next char may still change how the ones before it are normalized
+    fn continues(&self, last: char, next: char) -> bool {
+        let composing = matches!(self.form, Form::Nfc | Form::Nfkc);
+        canonical_combining_class(next) != 0 || composing && compose(last, next).is_some()
+    }
+
+    fn fill(&mut self) -> bool {
+        if self.idx < self.segment.len() {
+            return true;
+        }
+        let start = self.source.snapshot();
+        let Some(first) = self.source.next() else {
+            return false;
+        };
+        let mut raw = String::from(first);
+        let mut last = first;
+        loop {
+            let peeked = self.source.peek().copied();
+            match peeked {
+                Some(next) if self.continues(last, next) => {
+                    self.source.next();
+                    raw.push(next);
+                    last = next;
+                }
+                // Marks which are still to arrive would change the segment
+                None if self.source.is_partial() => {
+                    if let Err(error) = self.source.roll_back(start) {
+                        self.error = Some(error.into());
+                    }
+                    return false;
+                }
+                _ => break,
+            }
+        }
+        self.segment = normalize(&raw, self.form).chars().collect();
+        self.idx = 0;
+        !self.segment.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub struct NormalizedSnapshot<T> {
+    inner: T,
+    segment: Vec<char>,
+    idx: usize,
+}
+
+impl<S> Source for Normalized<S>
+where
+    S: Source<Item = char>,
+{
+    type Item = char;
+    type Snapshot = NormalizedSnapshot<S::Snapshot>;
+    type RollBackErr = S::RollBackErr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.fill() {
+            return None;
+        }
+        self.idx += 1;
+        Some(self.segment[self.idx - 1])
+    }
+
+    fn snapshot(&self) -> Self::Snapshot {
+        NormalizedSnapshot {
+            inner: self.source.snapshot(),
+            segment: self.segment.clone(),
+            idx: self.idx,
+        }
+    }
+
+    fn roll_back(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        self.source.roll_back(to.inner)?;
+        self.segment = to.segment;
+        self.idx = to.idx;
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Option<&Self::Item> {
+        if !self.fill() {
+            return None;
+        }
+        self.segment.get(self.idx)
+    }
+
+    fn peek_mut(&mut self) -> Option<&mut Self::Item> {
+        if !self.fill() {
+            return None;
+        }
+        self.segment.get_mut(self.idx)
+    }
+
+    #[inline]
+    fn context(&mut self) -> Option<&mut Context> {
+        self.source.context()
+    }
+
+    #[inline]
+    fn state(&mut self) -> Option<&mut dyn Any> {
+        self.source.state()
+    }
+
+    #[inline]
+    fn is_partial(&self) -> bool {
+        self.source.is_partial()
+    }
+
+    fn take_error(&mut self) -> Option<processed::Error> {
+        self.error.take().or_else(|| self.source.take_error())
+    }
+}
+
+// Yields the extended grapheme clusters of any char source, they're owned since they're put
+// together out of single chars
+pub struct GraphemeClusters<S> {
+    source: S,
+    position: Position,
+    peeked: Option<String>,
+    error: Option<processed::Error>,
+}
+
+impl<S> GraphemeClusters<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            position: Position::START,
+            peeked: None,
+            error: None,
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S> GraphemeClusters<S>
+where
+    S: Source<Item = char>,
+{
+    fn fill(&mut self) -> bool {
+        if self.peeked.is_some() {
+            return true;
+        }
+        let start = self.source.snapshot();
+        let Some(first) = self.source.next() else {
+            return false;
+        };
+        let mut cluster = String::from(first);
+        loop {
+            match self.source.peek().copied() {
+                Some(next) => {
+                    let len = cluster.len();
+                    cluster.push(next);
+                    if cluster.graphemes(true).nth(1).is_some() {
+                        cluster.truncate(len);
+                        break;
+                    }
+                    self.source.next();
+                }
+                // Chars which are still to arrive might extend the cluster
+                None if self.source.is_partial() => {
+                    if let Err(error) = self.source.roll_back(start) {
+                        self.error = Some(error.into());
+                    }
+                    return false;
+                }
+                None => break,
+            }
+        }
+        self.peeked = Some(cluster);
+        true
+    }
+}
+
+#[derive(Debug)]
+pub struct ClusterSnapshot<T> {
+    inner: T,
+    position: Position,
+    peeked: Option<String>,
+}
+
+impl<S> Source for GraphemeClusters<S>
+where
+    S: Source<Item = char>,
+{
+    type Item = String;
+    type Snapshot = ClusterSnapshot<S::Snapshot>;
+    type RollBackErr = S::RollBackErr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.fill() {
+            return None;
+        }
+        let next = self.peeked.take()?;
+        self.position.offset += 1;
+        if next == "\n" || next == "\r\n" {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(next)
+    }
+
+    fn snapshot(&self) -> Self::Snapshot {
+        ClusterSnapshot {
+            inner: self.source.snapshot(),
+            position: self.position,
+            peeked: self.peeked.clone(),
+        }
+    }
+
+    fn roll_back(&mut self, to: Self::Snapshot) -> Result<(), Self::RollBackErr> {
+        self.source.roll_back(to.inner)?;
+        self.position = to.position;
+        self.peeked = to.peeked;
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Option<&Self::Item> {
+        if !self.fill() {
+            return None;
+        }
+        self.peeked.as_ref()
+    }
+
+    fn peek_mut(&mut self) -> Option<&mut Self::Item> {
+        if !self.fill() {
+            return None;
+        }
+        self.peeked.as_mut()
+    }
+
+    #[inline]
+    fn context(&mut self) -> Option<&mut Context> {
+        self.source.context()
+    }
+
+    #[inline]
+    fn state(&mut self) -> Option<&mut dyn Any> {
+        self.source.state()
+    }
+
+    #[inline]
+    fn position(&self) -> Option<Position> {
+        Some(self.position)
+    }
+
+    #[inline]
+    fn is_partial(&self) -> bool {
+        self.source.is_partial()
+    }
+
+    fn take_error(&mut self) -> Option<processed::Error> {
+        self.error.take().or_else(|| self.source.take_error())
+    }
+}
@@ -0,0 +1,108 @@
+#![cfg(feature = "async")]
+
+use std::{
+    collections::VecDeque,
+    future::Future,
+    io,
+    pin::{pin, Pin},
+    task::{Context, Poll, Waker},
+};
+
+use futures_io::AsyncRead;
+use lingo_morph::{
+    async_io::{AsyncSource, AsyncStream},
+    binary::{take_bytes, uleb128},
+    context::ProcessingFailed,
+    processors::{just, satisfy},
+    Processor,
+};
+
+// Hands out the chunks one read at a time, like a socket would
+struct Chunks(VecDeque<&'static [u8]>);
+
+impl AsyncRead for Chunks {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let Some(chunk) = self.0.pop_front() else {
+            return Poll::Ready(Ok(0));
+        };
+        let read = chunk.len().min(buf.len());
+        buf[..read].copy_from_slice(&chunk[..read]);
+        if read < chunk.len() {
+            self.0.push_front(&chunk[read..]);
+        }
+        Poll::Ready(Ok(read))
+    }
+}
+
+fn block_on<F>(future: F) -> F::Output
+where
+    F: Future,
+{
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+fn word() -> impl Processor<u8, Output = String> {
+    satisfy(u8::is_ascii_alphabetic)
+        .fold(String::new, |mut word, next| {
+            word.push(char::from(next));
+            word
+        })
+        .ignore_next(just(b' '))
+}
+
+#[test]
+fn tokens_split_across_chunks_are_read_whole() {
+    let chunks = Chunks(VecDeque::from([&b"hel"[..], b"lo w", b"or", b"ld "]));
+    let mut source = AsyncSource::new(chunks);
+    let mut word = word();
+    let first = block_on(source.process(&mut word)).unwrap();
+    assert_eq!(first.output, "hello");
+    assert_eq!(source.offset(), 6);
+    let second = block_on(source.process(&mut word)).unwrap();
+    assert_eq!(second.output, "world");
+    assert!(source.buffered().is_empty());
+    // The reader is drained, so a word without its space can't be finished
+    let third = block_on(source.process(&mut word));
+    assert!(matches!(third, Err(ProcessingFailed::NoReturn)));
+    assert!(source.is_finished());
+}
+
+#[test]
+fn small_chunk_sizes_still_make_progress() {
+    let chunks = Chunks(VecDeque::from([&b"abc def "[..]]));
+    let mut source = AsyncSource::with_chunk_size(chunks, 1);
+    let mut word = word();
+    assert_eq!(block_on(source.process(&mut word)).unwrap().output, "abc");
+    assert_eq!(block_on(source.process(&mut word)).unwrap().output, "def");
+}
+
+#[test]
+fn failed_attempts_consume_nothing() {
+    // Too long for a varint, which fails only after reading all of it
+    static TOO_LONG: [u8; 11] = [0x80; 11];
+    let chunks = Chunks(VecDeque::from([&TOO_LONG[..4], &TOO_LONG[4..]]));
+    let mut source = AsyncSource::new(chunks);
+    let failed = block_on(source.process(&mut uleb128()));
+    assert!(matches!(failed, Err(ProcessingFailed::DuringProcessing(_))));
+    assert_eq!(source.offset(), 0);
+    let taken = block_on(source.process(&mut take_bytes(11))).unwrap();
+    assert_eq!(&*taken.output, TOO_LONG);
+    assert_eq!(source.offset(), 11);
+}
+
+#[test]
+fn streams_yield_every_output() {
+    let chunks = Chunks(VecDeque::from([&b"on"[..], b"e two", b" three "]));
+    let outputs = block_on(AsyncStream::new(chunks, word()).collect()).unwrap();
+    assert_eq!(outputs, ["one", "two", "three"]);
+}
@@ -0,0 +1,153 @@
+use lingo_morph::{
+    binary::{
+        f32_le, length_prefixed, sleb128, take_bytes, u16_be, u32_le, uleb128, zigzag_varint,
+        BinaryError,
+    },
+    context::ProcessingFailed,
+    processors::any,
+    source::{Borrowed, BoxedSlice, Source},
+    Processor,
+};
+
+fn binary_error<C>(failed: ProcessingFailed<C>) -> BinaryError {
+    match failed {
+        ProcessingFailed::DuringProcessing(error) => *error.downcast().unwrap(),
+        other => panic!("expected a binary error, got {other:?}"),
+    }
+}
+
+#[test]
+fn numbers_are_read_in_either_endianness() {
+    let input = [0x12, 0x34, 0x78, 0x56, 0x34, 0x12, 0, 0, 0x80, 0x3f];
+    let mut processor = u16_be().zip(u32_le()).zip(f32_le());
+    let diagnosed = processor
+        .with(Borrowed::from(&input[..]))
+        .process()
+        .unwrap();
+    assert_eq!(diagnosed.output, ((0x1234, 0x12345678), 1.0));
+}
+
+#[test]
+fn varints_decode() {
+    let mut unsigned = uleb128();
+    let diagnosed = unsigned
+        .with(BoxedSlice::from(vec![0xe5, 0x8e, 0x26]))
+        .process();
+    assert_eq!(diagnosed.unwrap().output, 624485);
+    let mut signed = sleb128();
+    let diagnosed = signed
+        .with(BoxedSlice::from(vec![0xc0, 0xbb, 0x78]))
+        .process();
+    assert_eq!(diagnosed.unwrap().output, -123456);
+    let mut zigzag = zigzag_varint();
+    let diagnosed = zigzag.with(BoxedSlice::from(vec![0x03])).process();
+    assert_eq!(diagnosed.unwrap().output, -2);
+}
+
+#[test]
+fn varints_past_64_bits_overflow() {
+    let mut too_long = vec![0xff; 9];
+    too_long.push(0x02);
+    let failed = uleb128()
+        .with(BoxedSlice::from(too_long.clone()))
+        .process()
+        .unwrap_err();
+    assert_eq!(binary_error(failed), BinaryError::VarintOverflow);
+    let failed = sleb128()
+        .with(BoxedSlice::from(too_long))
+        .process()
+        .unwrap_err();
+    assert_eq!(binary_error(failed), BinaryError::VarintOverflow);
+    // The tenth byte may still repeat the sign
+    let mut min = vec![0x80; 9];
+    min.push(0x7f);
+    let diagnosed = sleb128().with(BoxedSlice::from(min)).process().unwrap();
+    assert_eq!(diagnosed.output, i64::MIN);
+}
+
+#[test]
+fn borrowed_sources_lend_slices_of_the_input() {
+    let input = [1, 2, 3, 4, 5];
+    let bytes = {
+        let mut source = Borrowed::from(&input[..]);
+        source.next();
+        let bytes = source.take_slice(3).unwrap();
+        assert_eq!(source.next(), Some(5));
+        assert!(source.take_slice(1).is_none());
+        bytes
+    };
+    assert!(std::ptr::eq(bytes, &input[1..4]));
+}
+
+#[test]
+fn bytes_are_taken_at_once_from_slices() {
+    let mut processor = take_bytes(2);
+    let found: Vec<_> = processor
+        .with(BoxedSlice::from(vec![1, 2, 3, 4, 5]))
+        .find_iter()
+        .map(|found| {
+            let found = found.unwrap();
+            (found.output, found.span.start, found.span.end)
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [(Box::from([1, 2]), 0, 2), (Box::from([3, 4]), 2, 4)]
+    );
+}
+
+#[test]
+fn bytes_changed_through_peek_mut_are_taken_one_by_one() {
+    let input = [1u8, 2, 3];
+    let mut source = Borrowed::from(&input[..]);
+    *source.peek_mut().unwrap() = 10;
+    let diagnosed = take_bytes(2).zip(any()).with(source).process().unwrap();
+    assert_eq!(
+        (&*diagnosed.output.0, diagnosed.output.1),
+        (&[10, 2][..], 3)
+    );
+}
+
+#[test]
+fn length_prefixed_bodies_are_limited() {
+    let input = [3, b'a', b'b', b'c', b'd'];
+    let mut processor = length_prefixed(any(), take_bytes(3));
+    let diagnosed = processor
+        .with(Borrowed::from(&input[..]))
+        .process()
+        .unwrap();
+    assert_eq!(&*diagnosed.output, b"abc");
+    // Taking more than the length allows can't take past it
+    let mut processor = length_prefixed(any(), take_bytes(4));
+    assert!(processor
+        .with(Borrowed::from(&input[..]))
+        .process()
+        .is_err());
+    let mut processor = length_prefixed(any(), take_bytes(2));
+    let failed = processor
+        .with(Borrowed::from(&input[..]))
+        .process()
+        .unwrap_err();
+    assert_eq!(
+        binary_error(failed),
+        BinaryError::LengthMismatch {
+            expected: 3,
+            left: 1
+        }
+    );
+}
+
+#[test]
+fn borrowed_items_can_be_changed_through_peek_mut() {
+    let input = [1u8, 2, 3];
+    let mut source = Borrowed::from(&input[..]);
+    source.next();
+    let start = source.snapshot();
+    *source.peek_mut().unwrap() = 20;
+    assert_eq!(source.peek(), Some(&20));
+    assert!(source.as_slice().is_none());
+    assert_eq!(source.next(), Some(20));
+    source.roll_back(start).unwrap();
+    assert_eq!(source.iter().collect::<Vec<_>>(), [20, 3]);
+    assert_eq!(input, [1, 2, 3]);
+}
@@ -0,0 +1,62 @@
+use lingo_morph::{
+    bits::{align_to_byte, bits, bool_bit, BitOrder, BitSource},
+    source::{Borrowed, Source},
+    Processor,
+};
+
+#[test]
+fn bit_fields_are_read_most_significant_first() {
+    let input = [0b1011_0010, 0xff];
+    let mut processor = bits(3)
+        .unwrap()
+        .zip(bool_bit())
+        .ignore_next(align_to_byte())
+        .zip(bits(8).unwrap());
+    let diagnosed = processor
+        .with(BitSource::new(Borrowed::from(&input[..])))
+        .process()
+        .unwrap();
+    assert_eq!(diagnosed.output, ((0b101, true), 0xff));
+}
+
+#[test]
+fn bit_order_can_start_at_the_least_significant_bit() {
+    let input = [0b0000_0110];
+    let mut processor = bits(3).unwrap();
+    let source = BitSource::with_order(Borrowed::from(&input[..]), BitOrder::LsbFirst);
+    let diagnosed = processor.with(source).process().unwrap();
+    assert_eq!(diagnosed.output, 0b011);
+}
+
+#[test]
+fn snapshots_are_bit_granular() {
+    let input = [0b1010_0000];
+    let mut source = BitSource::new(Borrowed::from(&input[..]));
+    source.next();
+    let snapshot = source.snapshot();
+    assert_eq!(source.next(), Some(false));
+    assert_eq!(source.bit_offset(), 2);
+    source.roll_back(snapshot).unwrap();
+    assert_eq!(source.bit_offset(), 1);
+    assert_eq!(source.next(), Some(false));
+    assert_eq!(source.next(), Some(true));
+}
+
+#[test]
+fn too_wide_fields_are_refused() {
+    assert!(bits(65).is_none());
+    let input = [0];
+    let mut processor = bits(9).unwrap();
+    let source = BitSource::new(Borrowed::from(&input[..]));
+    assert!(processor.with(source).process().is_err());
+}
+
+#[test]
+fn bits_can_be_changed_through_peek_mut() {
+    let input = [0b1000_0000];
+    let mut source = BitSource::new(Borrowed::from(&input[..]));
+    *source.peek_mut().unwrap() = false;
+    assert_eq!(source.peek(), Some(&false));
+    assert_eq!(source.next(), Some(false));
+    assert_eq!(source.iter().filter(|bit| *bit).count(), 0);
+}
@@ -0,0 +1,178 @@
+use std::{cell::RefCell, rc::Rc};
+
+use lingo_morph::{
+    cst::{AstNode, GreenElement, SyntaxKind, SyntaxNode},
+    processed::Processed,
+    processors::{character, character_range, constant_with},
+    source::{BoxedSlice, Source, Span},
+    trace::Event,
+    trivia::Trivia,
+    Processor,
+};
+
+const ROOT: SyntaxKind = SyntaxKind(0);
+const LIST: SyntaxKind = SyntaxKind(1);
+const NUMBER: SyntaxKind = SyntaxKind(2);
+const PUNCT: SyntaxKind = SyntaxKind(3);
+
+fn punct(of: char) -> impl Processor<char, Output = char> {
+    character(of).token(PUNCT).lexeme()
+}
+
+fn number() -> impl Processor<char, Output = usize> {
+    let digits = character_range('0'..='9').fold(|| 1, |count, _| count + 1);
+    character_range('0'..='9')
+        .ignore(digits)
+        .token(NUMBER)
+        .lexeme()
+}
+
+fn item() -> impl Processor<char, Output = usize> {
+    number().map(|_| 1).or(List)
+}
+
+// Counts the numbers in a list like [1, [22, 3]]
+struct List;
+
+impl Processor<char> for List {
+    type Output = usize;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        let more = punct(',').ignore(item()).fold(|| 0, |sum, next| sum + next);
+        let items = item().zip(more).map(|(first, more)| first + more);
+        let mut list = punct('[')
+            .ignore(items.or(constant_with(|| 0)))
+            .ignore_next(punct(']'))
+            .node(LIST);
+        list.process(given)
+    }
+}
+
+struct ListNode(SyntaxNode);
+
+impl AstNode for ListNode {
+    fn can_cast(kind: SyntaxKind) -> bool {
+        kind == LIST
+    }
+
+    fn cast(node: SyntaxNode) -> Option<Self> {
+        Self::can_cast(node.kind()).then_some(Self(node))
+    }
+
+    fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+}
+
+fn tree(input: &str) -> (usize, SyntaxNode) {
+    let trivia = Trivia::new().whitespace(|x| x.is_whitespace());
+    let diagnosed = List
+        .with(BoxedSlice::from(input.chars().collect::<Vec<_>>()))
+        .trivia(trivia)
+        .syntax_tree(ROOT)
+        .unwrap();
+    diagnosed.output
+}
+
+#[test]
+fn trees_print_back_to_the_input() {
+    let input = "[1, [22 ,3]]  !?";
+    let (count, root) = tree(input);
+    assert_eq!(count, 3);
+    assert_eq!(root.to_string(), input);
+    assert_eq!(root.kind(), ROOT);
+    let kinds: Vec<_> = root
+        .children_with_tokens()
+        .iter()
+        .map(|child| child.kind())
+        .collect();
+    assert_eq!(kinds, [LIST, SyntaxKind::ERROR]);
+    let list = &root.children()[0];
+    assert_eq!(list.span(), Span::new(0, 14));
+    let texts: Vec<_> = list
+        .tokens()
+        .iter()
+        .map(|token| (token.kind(), token.text().to_owned()))
+        .collect();
+    assert_eq!(
+        texts,
+        [
+            (PUNCT, "[".to_owned()),
+            (NUMBER, "1".to_owned()),
+            (PUNCT, ",".to_owned()),
+            (SyntaxKind::TRIVIA, " ".to_owned()),
+            (PUNCT, "]".to_owned()),
+            (SyntaxKind::TRIVIA, "  ".to_owned()),
+        ]
+    );
+    assert_eq!(root.token(SyntaxKind::ERROR).unwrap().text(), "!?");
+}
+
+#[test]
+fn nodes_know_their_place() {
+    let (_, root) = tree("[1,[22,[3]]]");
+    let lists: Vec<_> = root
+        .descendants()
+        .into_iter()
+        .filter(|node| node.kind() == LIST)
+        .map(|node| node.span())
+        .collect();
+    assert_eq!(
+        lists,
+        [Span::new(0, 12), Span::new(3, 11), Span::new(7, 10)]
+    );
+    let innermost = root.covering(Span::new(8, 9));
+    assert_eq!(innermost.text(), "[3]");
+    assert_eq!(innermost.ancestors().count(), 4);
+    assert_eq!(innermost.parent().unwrap().text(), "[22,[3]]");
+    let number = innermost.token(NUMBER).unwrap();
+    assert_eq!(number.span(), Span::new(8, 9));
+    assert_eq!(number.parent(), &innermost);
+    let typed = root.children()[0].children_of::<ListNode>();
+    assert_eq!(typed.len(), 1);
+    assert_eq!(typed[0].syntax().span(), Span::new(3, 11));
+    assert!(root.child::<ListNode>().is_some());
+}
+
+#[test]
+fn replacing_a_node_shares_the_rest() {
+    let (_, root) = tree("[1,[2],3]");
+    let (_, other) = tree("[45]");
+    let inner = root.covering(Span::new(4, 5));
+    let GreenElement::Node(replacement) = &other.green().children()[0] else {
+        panic!("expected a list node");
+    };
+    let replaced = inner.replace_with(replacement.clone());
+    assert_eq!(replaced.to_string(), "[1,[45],3]");
+    assert_eq!(replaced.span(), Span::new(0, 10));
+    let before = root.children()[0].green().children()[1].clone();
+    let after = replaced.children()[0].green().children()[1].clone();
+    match (before, after) {
+        (GreenElement::Token(before), GreenElement::Token(after)) => {
+            assert!(Rc::ptr_eq(&before, &after))
+        }
+        _ => panic!("expected the number token"),
+    }
+}
+
+#[test]
+fn reading_the_input_again_is_not_a_rollback() {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let recorded = events.clone();
+    let tracer = Rc::new(RefCell::new(move |event| recorded.borrow_mut().push(event)));
+    let diagnosed = character('1')
+        .token(NUMBER)
+        .with(BoxedSlice::from(vec!['1', '2']))
+        .tracer(tracer)
+        .syntax_tree(ROOT)
+        .unwrap();
+    assert_eq!(diagnosed.output.1.to_string(), "12");
+    let rolled_back = events
+        .borrow()
+        .iter()
+        .any(|event| matches!(event, Event::Rollback { .. }));
+    assert!(!rolled_back);
+}
@@ -0,0 +1,95 @@
+use lingo_morph::{
+    context::{self, Severity},
+    done,
+    processed::Processed,
+    processors::{any, character},
+    source::{BoxedSlice, Source},
+    try_done, Processor,
+};
+
+// Takes any char, warning about uppercase ones
+struct Shouted;
+
+impl Processor<char> for Shouted {
+    type Output = char;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        let (next, mut rest) = try_done!(any().process(given));
+        if next.is_uppercase() {
+            context::warn(&mut rest, format!("{next} is shouted"));
+        }
+        done(next, rest)
+    }
+}
+
+fn chars(input: &str) -> BoxedSlice<char> {
+    BoxedSlice::from(input.chars().collect::<Vec<_>>())
+}
+
+#[test]
+fn diagnostics_are_returned_with_the_output() {
+    let mut processor = Shouted.fold(String::new, |mut text, next| {
+        text.push(next);
+        text
+    });
+    let diagnosed = processor.with(chars("aBcD")).process().unwrap();
+    assert_eq!(diagnosed.output, "aBcD");
+    let reported: Vec<_> = diagnosed
+        .diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.severity(), diagnostic.offset()))
+        .collect();
+    assert_eq!(reported, [(Severity::Warning, 2), (Severity::Warning, 4)]);
+    assert_eq!(diagnosed.diagnostics[0].message(), "B is shouted");
+}
+
+#[test]
+fn rolled_back_diagnostics_are_dropped() {
+    let mut processor = Shouted.ignore(character('x')).or(any());
+    let diagnosed = processor.with(chars("Ay")).process().unwrap();
+    assert_eq!(diagnosed.output, 'A');
+    assert!(diagnosed.diagnostics.is_empty());
+}
+
+#[test]
+fn morphing_keeps_the_diagnostics_of_matches() {
+    let mut processor = Shouted.ignore(character('!'));
+    let morphed = processor.with(chars("a!B!C")).morph("_").unwrap();
+    assert_eq!(morphed.output, "__C");
+    let offsets: Vec<_> = morphed
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.offset())
+        .collect();
+    assert_eq!(offsets, [3]);
+}
+
+#[test]
+fn notes_are_reported_at_the_offset() {
+    let mut processor = any().ignore_next(character('!')).map(|_| ());
+    let mut processor = NoteAfter(&mut processor);
+    let diagnosed = processor.with(chars("a!")).process().unwrap();
+    assert_eq!(diagnosed.diagnostics.len(), 1);
+    assert_eq!(diagnosed.diagnostics[0].to_string(), "note at 2: finished");
+}
+
+struct NoteAfter<P>(P);
+
+impl<P> Processor<char> for NoteAfter<P>
+where
+    P: Processor<char>,
+{
+    type Output = P::Output;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        let (output, mut rest) = try_done!(self.0.process(given));
+        context::note(&mut rest, "finished");
+        done(output, rest)
+    }
+}
@@ -0,0 +1,61 @@
+use lingo_morph::{
+    context::ProcessingFailed,
+    encoding::{DecodeError, Decoder, Encoding, Malformed},
+    processors::any,
+    source::{Borrowed, Source},
+    Processor,
+};
+
+fn decode(bytes: &[u8], encoding: Encoding) -> String {
+    Decoder::new(Borrowed::from(bytes), encoding)
+        .iter()
+        .collect()
+}
+
+#[test]
+fn text_is_decoded_in_each_encoding() {
+    assert_eq!(decode("añ€😀".as_bytes(), Encoding::Utf8), "añ€😀");
+    let utf16: Vec<u16> = "a😀".encode_utf16().collect();
+    let le: Vec<u8> = utf16.iter().flat_map(|unit| unit.to_le_bytes()).collect();
+    let be: Vec<u8> = utf16.iter().flat_map(|unit| unit.to_be_bytes()).collect();
+    assert_eq!(decode(&le, Encoding::Utf16Le), "a😀");
+    assert_eq!(decode(&be, Encoding::Utf16Be), "a😀");
+    assert_eq!(decode(&[0x61, 0xe9], Encoding::Latin1), "aé");
+}
+
+#[test]
+fn byte_order_marks_pick_the_encoding() {
+    let bytes = [0xff, 0xfe, b'h', 0, b'i', 0];
+    let mut decoder = Decoder::detect(Borrowed::from(&bytes[..]));
+    assert_eq!(decoder.iter().collect::<String>(), "hi");
+    assert_eq!(decoder.encoding(), Some(Encoding::Utf16Le));
+    let bytes = [0xef, 0xbb, 0xbf, b'o', b'k'];
+    let mut decoder = Decoder::detect(Borrowed::from(&bytes[..]));
+    assert_eq!(decoder.iter().collect::<String>(), "ok");
+    assert_eq!(decoder.byte_offset(), 5);
+}
+
+#[test]
+fn malformed_sequences_are_replaced_by_default() {
+    assert_eq!(decode(&[b'a', 0xc0, b'b'], Encoding::Utf8), "a\u{fffd}b");
+    assert_eq!(decode(&[0xe2, 0x82, b'x'], Encoding::Utf8), "\u{fffd}x");
+}
+
+#[test]
+fn malformed_sequences_can_be_errors() {
+    let bytes = [b'a', b'b', 0xff];
+    let decoder =
+        Decoder::new(Borrowed::from(&bytes[..]), Encoding::Utf8).malformed(Malformed::Error);
+    let mut processor = any().fold(String::new, |mut text, next| {
+        text.push(next);
+        text
+    });
+    match processor.with(decoder).process().unwrap_err() {
+        ProcessingFailed::DuringProcessing(error) => {
+            let error = error.downcast::<DecodeError>().unwrap();
+            assert_eq!(error.offset(), 2);
+            assert_eq!(error.encoding(), Encoding::Utf8);
+        }
+        other => panic!("expected a decode error, got {other:?}"),
+    }
+}
@@ -0,0 +1,88 @@
+use lingo_morph::{
+    binary::take_bytes,
+    done,
+    grammar::{Describe, Expr, Grammar},
+    processed::Processed,
+    processors::{character, character_range},
+    source::Source,
+    try_done, Processor,
+};
+
+fn number() -> impl Processor<char, Output = ()> + Describe {
+    let digit = character_range('0'..='9');
+    let digits = digit.fold(|| (), |_, _| ());
+    digit.ignore(digits).named("number")
+}
+
+fn list() -> impl Processor<char, Output = ()> + Describe {
+    let items = Value.ignore_next(character(';')).fold(|| (), |_, _| ());
+    character('[')
+        .ignore(items)
+        .ignore_next(character(']'))
+        .named("list")
+}
+
+fn value() -> impl Processor<char, Output = ()> + Describe {
+    number().or(list()).or(character('"').map(|_| ()))
+}
+
+// Lists hold values, so describing one has to stop at the rule being defined
+struct Value;
+
+impl Processor<char> for Value {
+    type Output = ();
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        let (_, rest) = try_done!(value().process(given));
+        done((), rest)
+    }
+}
+
+impl Describe for Value {
+    fn describe(&self, grammar: &mut Grammar) -> Expr {
+        grammar.define("value", &value())
+    }
+}
+
+#[test]
+fn recursive_grammars_render_as_ebnf() {
+    let grammar = Grammar::of(&Value);
+    assert_eq!(
+        grammar.to_ebnf(),
+        concat!(
+            "value = number | list | '\"' ;\n",
+            "number = ? \"0\" to \"9\" ? , { ? \"0\" to \"9\" ? } ;\n",
+            "list = \"[\" , { value , \";\" } , \"]\" ;\n",
+        )
+    );
+}
+
+#[test]
+fn recursive_grammars_render_as_abnf() {
+    let grammar = Grammar::of(&Value);
+    assert_eq!(
+        grammar.to_abnf(),
+        concat!(
+            "value = number / list / %x22\n",
+            "number = %x30-39 *%x30-39\n",
+            "list = \"[\" *(value \";\") \"]\"\n",
+        )
+    );
+}
+
+#[test]
+fn only_take_bounds_a_fold_over_it() {
+    let taken = character('a').take(3).fold(|| (), |_, _| ());
+    assert_eq!(Grammar::of(&taken).to_ebnf(), "start = 3 * [ \"a\" ] ;\n");
+    assert_eq!(
+        Grammar::of(&character('a').take(3)).to_ebnf(),
+        "start = [ \"a\" ] ;\n"
+    );
+    let nested = character('a').fold(|| (), |_, _| ()).fold(|| (), |_, _| ());
+    assert_eq!(Grammar::of(&nested).to_ebnf(), "start = { { \"a\" } } ;\n");
+    let bytes = take_bytes(4).fold(|| (), |_, _| ());
+    assert_eq!(Grammar::of(&bytes).to_abnf(), "start = *(4<any>)\n");
+}
@@ -0,0 +1,259 @@
+use std::{cell::Cell, rc::Rc};
+
+use lingo_morph::{
+    context::{self, CancellationToken, Diagnosed, LimitExceeded, Limits, ProcessingFailed},
+    cst::{SyntaxKind, SyntaxNode},
+    done,
+    incremental::{Incremental, TextEdit},
+    indent::indented_block,
+    processed::Processed,
+    processors::{character, character_range, constant_with},
+    source::{Source, Span},
+    trivia::Trivia,
+    try_done, Processor,
+};
+
+const ROOT: SyntaxKind = SyntaxKind(0);
+const LIST: SyntaxKind = SyntaxKind(1);
+const NUMBER: SyntaxKind = SyntaxKind(2);
+const PUNCT: SyntaxKind = SyntaxKind(3);
+
+fn punct(of: char) -> impl Processor<char, Output = char> {
+    character(of).token(PUNCT).lexeme()
+}
+
+// Warns about leading zeros
+struct Number;
+
+impl Processor<char> for Number {
+    type Output = usize;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        let digits = character_range('0'..='9').fold(String::new, |mut text, next| {
+            text.push(next);
+            text
+        });
+        let mut number = character_range('0'..='9').zip(digits).token(NUMBER);
+        let ((first, rest), mut rest_source) = try_done!(number.process(given));
+        if first == '0' && !rest.is_empty() {
+            context::warn(&mut rest_source, "leading zero");
+        }
+        done(1, rest_source)
+    }
+}
+
+fn item() -> impl Processor<char, Output = usize> {
+    Number.lexeme().or(List)
+}
+
+struct List;
+
+impl Processor<char> for List {
+    type Output = usize;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        let more = punct(',').ignore(item()).fold(|| 0, |sum, next| sum + next);
+        let items = item().zip(more).map(|(first, more)| first + more);
+        let mut list = punct('[')
+            .ignore(items.or(constant_with(|| 0)))
+            .ignore_next(punct(']'))
+            .node(LIST);
+        list.process(given)
+    }
+}
+
+// Counts how often the reparser gets used
+struct Reparsing(Rc<Cell<usize>>);
+
+impl Processor<char> for Reparsing {
+    type Output = usize;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        self.0.set(self.0.get() + 1);
+        List.process(given)
+    }
+}
+
+fn incremental(reparsed: Rc<Cell<usize>>) -> Incremental<'static> {
+    Incremental::new(ROOT, List)
+        .reparser(LIST, Reparsing(reparsed))
+        .trivia(Trivia::new().whitespace(|x| x.is_whitespace()))
+}
+
+fn assert_same(incremental: &Diagnosed<SyntaxNode>, full: &Diagnosed<SyntaxNode>, text: &str) {
+    assert_eq!(incremental.output.to_string(), text);
+    assert_eq!(incremental.output.green(), full.output.green(), "{text}");
+    assert_eq!(incremental.diagnostics, full.diagnostics, "{text}");
+}
+
+// Small xorshift so the edits are random but the same on every run
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % bound as u64) as usize
+    }
+
+    fn text(&mut self) -> String {
+        const PIECES: [&str; 8] = ["1", "0", "07", ",", "[", "]", " ", "[2, 3]"];
+        (0..self.below(3))
+            .map(|_| PIECES[self.below(PIECES.len())])
+            .collect()
+    }
+
+    fn edit(&mut self, len: usize) -> TextEdit {
+        let start = self.below(len + 1);
+        let end = match self.below(3) {
+            // Insertion
+            0 => start,
+            // Deletion or replacement
+            _ => start + self.below(len - start + 1).min(4),
+        };
+        let replacement = match end > start && self.below(2) == 0 {
+            true => String::new(),
+            false => self.text(),
+        };
+        TextEdit::new(Span::new(start, end), replacement)
+    }
+}
+
+#[test]
+fn edits_inside_a_node_are_reparsed_locally() {
+    let reparsed = Rc::new(Cell::new(0));
+    let mut parser = incremental(reparsed.clone());
+    let before = parser.parse("[1, [2, 03], [4]] ").unwrap();
+    assert_eq!(before.diagnostics.len(), 1);
+    assert_eq!(before.diagnostics[0].offset(), 10);
+    let edit = TextEdit::new(Span::new(5, 6), "00, 5");
+    let after = parser.edit(&before, &edit).unwrap();
+    assert_eq!(reparsed.get(), 1);
+    let text = edit.apply(&before.output.text());
+    assert_same(&after, &parser.parse(&text).unwrap(), &text);
+    assert_eq!(after.diagnostics[0].offset(), 7);
+    assert_eq!(after.diagnostics[1].offset(), 14);
+    // The lists around the edit are shared with the previous tree
+    let last = |tree: &SyntaxNode| tree.children()[0].children()[1].green().clone();
+    assert!(Rc::ptr_eq(&last(&before.output), &last(&after.output)));
+}
+
+#[test]
+fn random_edits_match_a_full_reparse() {
+    const SEED: &str = "[1, [2, 03, [4 ,5]], [], [06,[7]]]";
+    let reparsed = Rc::new(Cell::new(0));
+    let mut parser = incremental(reparsed.clone());
+    let mut fresh = incremental(Rc::default());
+    let mut random = Random(0x2545_f491_4f6c_dd1d);
+    let mut current = parser.parse(SEED).unwrap();
+    for _ in 0..2000 {
+        let text = current.output.text();
+        let edit = random.edit(text.chars().count());
+        let edited = edit.apply(&text);
+        match (parser.edit(&current, &edit), fresh.parse(&edited)) {
+            (Ok(incremental), Ok(full)) => {
+                assert_same(&incremental, &full, &edited);
+                current = incremental;
+            }
+            (Err(_), Err(_)) => current = parser.parse(SEED).unwrap(),
+            (incremental, full) => panic!("{edited:?} gave {incremental:?} and {full:?}"),
+        }
+    }
+    assert!(
+        reparsed.get() > 100,
+        "only reparsed {} times",
+        reparsed.get()
+    );
+}
+
+#[test]
+fn reparses_keep_the_limits_and_cancellation() {
+    let before = incremental(Rc::default())
+        .parse("[1, [2, 03], [4]] ")
+        .unwrap();
+    let edit = TextEdit::new(Span::new(5, 6), "00, 5");
+    let reparsed = Rc::new(Cell::new(0));
+    let mut limited = incremental(reparsed.clone()).limits(Limits::new().steps(5));
+    let failed = limited.edit(&before, &edit).unwrap_err();
+    assert!(matches!(
+        failed,
+        ProcessingFailed::LimitExceeded(LimitExceeded::Steps(5))
+    ));
+    assert_eq!(reparsed.get(), 1);
+    let token = CancellationToken::new();
+    token.cancel();
+    let mut cancelled = incremental(Rc::default()).cancellation(token);
+    let failed = cancelled.edit(&before, &edit).unwrap_err();
+    assert!(matches!(failed, ProcessingFailed::Cancelled(_)));
+}
+
+#[test]
+fn reparses_count_steps_from_the_node() {
+    let text = "[1, [2, 03], [4]] ";
+    let before = incremental(Rc::default()).parse(text).unwrap();
+    let edit = TextEdit::new(Span::new(5, 6), "0");
+    let limits = Limits::new().steps(50);
+    // The limit is enough for the node, but not for all of the input
+    let failed = incremental(Rc::default())
+        .limits(limits)
+        .parse(&edit.apply(text))
+        .unwrap_err();
+    assert!(matches!(failed, ProcessingFailed::LimitExceeded(_)));
+    let reparsed = Rc::new(Cell::new(0));
+    let mut limited = incremental(reparsed.clone()).limits(limits);
+    let after = limited.edit(&before, &edit).unwrap();
+    assert_eq!(reparsed.get(), 1);
+    assert_eq!(after.output.to_string(), "[1, [0, 03], [4]] ");
+}
+
+const ENTRY: SyntaxKind = SyntaxKind(4);
+
+fn name() -> impl Processor<char, Output = String> {
+    character_range('a'..='z').fold(String::new, |mut name, next| {
+        name.push(next);
+        name
+    })
+}
+
+// A name, optionally followed by a colon and the indented entries under it
+struct Entry;
+
+impl Processor<char> for Entry {
+    type Output = usize;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        let children = indented_block(Entry).map(|entries| entries.into_iter().sum());
+        let under = character(':').ignore(children.or(constant_with(|| 0)));
+        let mut entry = name()
+            .ignore(under.or(constant_with(|| 0)))
+            .map(|count| count + 1)
+            .node(ENTRY);
+        entry.process(given)
+    }
+}
+
+#[test]
+fn reparses_start_inside_the_blocks_of_the_node() {
+    let mut parser = Incremental::new(ROOT, Entry).reparser(ENTRY, Entry);
+    let before = parser.parse("a:\n  b:\n    c\n  d").unwrap();
+    // The line no longer is deeper than the block b is in, so it's not under b anymore
+    let edit = TextEdit::new(Span::new(9, 11), "");
+    let after = parser.edit(&before, &edit).unwrap();
+    let text = edit.apply(&before.output.text());
+    assert_same(&after, &parser.parse(&text).unwrap(), &text);
+    let entries = after.output.children()[0].children();
+    assert_eq!(entries.len(), 3);
+}
@@ -0,0 +1,162 @@
+use lingo_morph::{
+    context::ProcessingFailed,
+    done, err,
+    indent::{indentation, indented_block, same_indent, Indent, IndentError},
+    mismatch,
+    processed::Processed,
+    processors::{character, character_range},
+    source::{BoxedSlice, Located, Source},
+    Processor,
+};
+
+fn located(input: &str) -> Located<BoxedSlice<char>> {
+    Located::new(BoxedSlice::from(input.chars().collect::<Vec<_>>()))
+}
+
+fn word() -> impl Processor<char, Output = String> {
+    character_range('a'..='z').fold(String::new, |mut word, next| {
+        word.push(next);
+        word
+    })
+}
+
+fn block() -> impl Processor<char, Output = (String, Vec<String>)> {
+    word()
+        .ignore_next(character(':'))
+        .zip(indented_block(word()))
+}
+
+fn indent_error<C>(failed: ProcessingFailed<C>) -> IndentError {
+    match failed {
+        ProcessingFailed::DuringProcessing(error) => *error.downcast().unwrap(),
+        other => panic!("expected an indentation error, got {other:?}"),
+    }
+}
+
+#[test]
+fn indented_lines_form_a_block() {
+    let mut processor = block();
+    let diagnosed = processor
+        .with(located("head:\n  one\n\n  two\nnext"))
+        .process()
+        .unwrap();
+    assert_eq!(diagnosed.output.0, "head");
+    assert_eq!(diagnosed.output.1, ["one", "two"]);
+}
+
+#[test]
+fn content_on_the_same_line_is_not_a_block() {
+    let mut processor = block();
+    let failed = processor.with(located("head: one")).process().unwrap_err();
+    assert!(matches!(failed, ProcessingFailed::NoReturn));
+}
+
+#[test]
+fn content_on_the_same_line_ends_the_block() {
+    let mut processor = block();
+    let diagnosed = processor
+        .with(located("head:\n  one  two\n  three"))
+        .process()
+        .unwrap();
+    assert_eq!(diagnosed.output.1, ["one"]);
+}
+
+#[test]
+fn same_indent_continues_at_the_current_level() {
+    let mut processor = word().ignore_next(character('\n')).zip(same_indent(word()));
+    let diagnosed = processor.with(located("one\ntwo")).process().unwrap();
+    assert_eq!(diagnosed.output, ("one".into(), "two".into()));
+    let failed = processor.with(located("one\n  two")).process().unwrap_err();
+    assert!(matches!(failed, ProcessingFailed::NoReturn));
+}
+
+#[test]
+fn mixed_tabs_and_spaces_are_reported() {
+    let mut processor = block();
+    let failed = processor
+        .with(located("head:\n  one\n\ttwo"))
+        .process()
+        .unwrap_err();
+    let error = indent_error(failed);
+    assert!(matches!(error, IndentError::Inconsistent { .. }));
+    assert!(error.to_string().contains("line 3, column 1"));
+}
+
+#[test]
+fn deeper_lines_inside_a_block_are_unexpected() {
+    let mut processor = block();
+    let failed = processor
+        .with(located("head:\n  one\n    two"))
+        .process()
+        .unwrap_err();
+    assert!(matches!(
+        indent_error(failed),
+        IndentError::Unexpected { .. }
+    ));
+}
+
+#[test]
+fn blocks_need_positions() {
+    let mut processor = block();
+    let unlocated = BoxedSlice::from("head:\n  one".chars().collect::<Vec<_>>());
+    let failed = processor.with(unlocated).process().unwrap_err();
+    assert_eq!(indent_error(failed), IndentError::NoPosition);
+}
+
+#[test]
+fn indentation_is_measured_at_line_start() {
+    let mut processor = indentation();
+    let diagnosed = processor.with(located(" \t x")).process().unwrap();
+    assert_eq!(diagnosed.output.as_str(), " \t ");
+    assert_eq!(diagnosed.output.width(), 3);
+}
+
+// Swaps the current indent for an empty one before giving up
+struct Dedent;
+
+impl Processor<char> for Dedent {
+    type Output = Option<Indent>;
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        let fallback = given.snapshot();
+        if let Some(context) = given.context() {
+            context.pop_indent();
+            context.push_indent(Indent::default());
+        }
+        match given.roll_back(fallback) {
+            Ok(()) => mismatch(given),
+            Err(error) => err(error),
+        }
+    }
+}
+
+struct Current;
+
+impl Processor<char> for Current {
+    type Output = Option<Indent>;
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        let current = given
+            .context()
+            .and_then(|context| context.indent().cloned());
+        done(current, given)
+    }
+}
+
+#[test]
+fn swapped_indents_are_rolled_back() {
+    let mut processor = indented_block(Dedent.or(Current).ignore_next(word()));
+    let diagnosed = processor.with(located("\n  one\n  two")).process().unwrap();
+    let widths: Vec<_> = diagnosed
+        .output
+        .iter()
+        .map(|indent| indent.as_ref().map(Indent::width))
+        .collect();
+    assert_eq!(widths, [Some(2), Some(2)]);
+}
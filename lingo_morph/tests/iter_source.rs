@@ -0,0 +1,49 @@
+use lingo_morph::{
+    processors::{any, character},
+    source::{IterSource, Source},
+    Processor,
+};
+
+#[test]
+fn iterators_can_be_processed() {
+    let mut processor = character('a')
+        .ignore(character('x'))
+        .or(character('a').ignore(character('b')))
+        .fold(String::new, |mut text, next| {
+            text.push(next);
+            text
+        });
+    let diagnosed = processor
+        .with(IterSource::new("ababab".chars()))
+        .process()
+        .unwrap();
+    assert_eq!(diagnosed.output, "bbb");
+}
+
+#[test]
+fn rolling_back_replays_buffered_items() {
+    let mut source = IterSource::new(1..);
+    assert_eq!(source.next(), Some(1));
+    let snapshot = source.snapshot();
+    assert_eq!(source.next(), Some(2));
+    assert_eq!(source.next(), Some(3));
+    source.roll_back(snapshot).unwrap();
+    assert_eq!(source.next(), Some(2));
+    assert_eq!(source.peek(), Some(&3));
+}
+
+#[test]
+fn items_are_only_buffered_while_snapshots_live() {
+    let mut source = IterSource::new(0..100);
+    let snapshot = source.snapshot();
+    for _ in 0..10 {
+        source.next();
+    }
+    assert_eq!(source.buffered(), 10);
+    drop(snapshot);
+    source.next();
+    assert_eq!(source.buffered(), 0);
+    let mut counted = any().fold(|| 0, |count, _| count + 1);
+    let diagnosed = counted.with(source).process().unwrap();
+    assert_eq!(diagnosed.output, 89);
+}
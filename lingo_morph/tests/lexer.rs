@@ -0,0 +1,95 @@
+use lingo_morph::{
+    context::ProcessingFailed,
+    lexer::{kind, LexError, Lexer, Token},
+    processors::{character, character_range},
+    source::{BoxedSlice, Source, Span},
+    Processor,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    If,
+    Ident,
+    Number,
+    Plus,
+}
+
+fn chars(input: &str) -> BoxedSlice<char> {
+    BoxedSlice::from(input.chars().collect::<Vec<_>>())
+}
+
+fn lexer<'a>() -> Lexer<'a, BoxedSlice<char>, Kind> {
+    let ident = character_range('a'..='z').fold(|| Kind::Ident, |kind, _| kind);
+    let number = character_range('0'..='9').fold(|| Kind::Number, |kind, _| kind);
+    let keyword = character('i').zip(character('f')).replace(Kind::If);
+    let blank = character(' ').fold(|| (), |_, _| ());
+    Lexer::new()
+        .rule(keyword)
+        .rule(ident)
+        .rule(number)
+        .rule(character('+').replace(Kind::Plus))
+        .skip(blank)
+}
+
+fn kinds(input: &str) -> Vec<(Kind, String, Span)> {
+    let mut lexer = lexer();
+    let mut tokens = lexer.tokens(chars(input));
+    let found = tokens
+        .iter()
+        .map(|token| (token.kind, token.text, token.span))
+        .collect();
+    assert!(tokens.take_error().is_none());
+    found
+}
+
+#[test]
+fn input_is_cut_into_tokens() {
+    assert_eq!(
+        kinds("if x + 42"),
+        [
+            (Kind::If, "if".into(), Span::new(0, 2)),
+            (Kind::Ident, "x".into(), Span::new(3, 4)),
+            (Kind::Plus, "+".into(), Span::new(5, 6)),
+            (Kind::Number, "42".into(), Span::new(7, 9)),
+        ]
+    );
+}
+
+#[test]
+fn the_longest_match_wins() {
+    let found = kinds("iffy if");
+    assert_eq!(found[0].0, Kind::Ident);
+    assert_eq!(found[0].1, "iffy");
+    // Equally long, the rule added first wins
+    assert_eq!(found[1].0, Kind::If);
+}
+
+#[test]
+fn processors_run_over_tokens() {
+    let mut lexer = lexer();
+    let mut sum = kind(Kind::Number)
+        .ignore_next(kind(Kind::Plus))
+        .zip(kind(Kind::Number))
+        .map(|(left, right): (Token<Kind>, Token<Kind>)| {
+            left.text.parse::<u32>().unwrap() + right.text.parse::<u32>().unwrap()
+        });
+    let diagnosed = sum.with(lexer.tokens(chars("1 + 2"))).process().unwrap();
+    assert_eq!(diagnosed.output, 3);
+}
+
+#[test]
+fn unknown_input_is_a_lex_error() {
+    let mut lexer = lexer();
+    let mut numbers = kind(Kind::Number).fold(|| 0, |count, _| count + 1);
+    let failed = numbers
+        .with(lexer.tokens(chars("1 2 ?")))
+        .process()
+        .unwrap_err();
+    match failed {
+        ProcessingFailed::DuringProcessing(error) => {
+            let error = error.downcast::<LexError>().unwrap();
+            assert_eq!(error.position().offset, 4);
+        }
+        other => panic!("expected a lex error, got {other:?}"),
+    }
+}
@@ -0,0 +1,144 @@
+use std::{cell::Cell, thread, time::Duration};
+
+use lingo_morph::{
+    context::{CancellationToken, LimitExceeded, Limits, ProcessingFailed},
+    processors::{any, character, satisfy},
+    source::{BoxedSlice, Source},
+    Processor,
+};
+
+fn chars(input: &str) -> BoxedSlice<char> {
+    BoxedSlice::from(input.chars().collect::<Vec<_>>())
+}
+
+#[test]
+fn repetitions_of_leaf_processors_count_as_steps() {
+    let mut processor = any().fold(|| 0, |count, _: char| count + 1);
+    let limits = Limits::new().steps(10);
+    let failed = processor
+        .with(chars(&"x".repeat(100)))
+        .limits(limits)
+        .process()
+        .unwrap_err();
+    assert!(matches!(
+        failed,
+        ProcessingFailed::LimitExceeded(LimitExceeded::Steps(10))
+    ));
+    let diagnosed = processor.with(chars("xxxx")).limits(limits).process();
+    assert_eq!(diagnosed.unwrap().output, 4);
+}
+
+#[test]
+fn the_deadline_is_checked_while_repeating() {
+    let mut processor = satisfy(|_: &char| {
+        thread::sleep(Duration::from_millis(1));
+        true
+    })
+    .fold(|| (), |_, _| ());
+    let limits = Limits::new().timeout(Duration::from_millis(20));
+    let failed = processor
+        .with(chars(&"x".repeat(200)))
+        .limits(limits)
+        .process()
+        .unwrap_err();
+    assert!(matches!(
+        failed,
+        ProcessingFailed::LimitExceeded(LimitExceeded::Deadline)
+    ));
+}
+
+#[test]
+fn drivers_moving_on_are_not_rollbacks() {
+    let mut processor = character('a').zip(character('b'));
+    let limits = Limits::new().rollbacks(0);
+    let found = processor
+        .with(chars("axaxaxab"))
+        .limits(limits)
+        .find_iter()
+        .map(|found| found.unwrap().span.start)
+        .collect::<Vec<_>>();
+    assert_eq!(found, [6]);
+    let morphed = processor
+        .with(chars("axaxaxab"))
+        .limits(limits)
+        .morph("!")
+        .unwrap()
+        .output;
+    assert_eq!(morphed, "axaxax!");
+}
+
+#[test]
+fn backtracking_processors_count_their_rollbacks() {
+    let mut processor = character('a')
+        .ignore(character('b'))
+        .or(character('a'))
+        .fold(|| 0, |count, _| count + 1);
+    let failed = processor
+        .with(chars("aaaa"))
+        .limits(Limits::new().rollbacks(2))
+        .process()
+        .unwrap_err();
+    assert!(matches!(
+        failed,
+        ProcessingFailed::LimitExceeded(LimitExceeded::Rollbacks(2))
+    ));
+}
+
+#[test]
+fn the_fold_driver_tells_limits_and_cancellation_apart() {
+    let mut processor = any::<char>();
+    let failed = processor
+        .with(chars(&"x".repeat(100)))
+        .limits(Limits::new().steps(10))
+        .fold(0, |count, _| count + 1)
+        .unwrap_err();
+    assert!(matches!(
+        failed,
+        ProcessingFailed::LimitExceeded(LimitExceeded::Steps(10))
+    ));
+    let token = CancellationToken::new();
+    token.cancel();
+    let failed = processor
+        .with(chars("xyz"))
+        .cancellation(token)
+        .fold(0, |count, _| count + 1)
+        .unwrap_err();
+    assert!(matches!(failed, ProcessingFailed::Cancelled(_)));
+    let mut state = 0;
+    let diagnosed = processor
+        .with(chars("xyz"))
+        .fold_with_state(&mut state, String::new(), |mut text, next| {
+            text.insert(0, next);
+            text
+        })
+        .unwrap();
+    assert_eq!(diagnosed.output, "zyx");
+}
+
+#[test]
+fn cancelled_processing_hands_back_the_source_to_resume_on() {
+    let token = CancellationToken::new();
+    let seen = Cell::new(0);
+    let mut processor = satisfy(|_: &char| {
+        seen.set(seen.get() + 1);
+        if seen.get() == 3 {
+            token.cancel();
+        }
+        true
+    })
+    .fold(|| 0, |count, _| count + 1);
+    let failed = processor
+        .with(chars("xxxxx"))
+        .cancellation(token.clone())
+        .process()
+        .unwrap_err();
+    let ProcessingFailed::Cancelled(given) = failed else {
+        panic!("{failed:?}");
+    };
+    let (mut source, context) = given.into_parts();
+    assert_eq!(context.offset(), 0);
+    assert_eq!(context.usage().depth, 0);
+    assert_eq!(source.peek(), Some(&'x'));
+    let diagnosed = processor.with(source).process();
+    assert_eq!(diagnosed.unwrap().output, 5);
+}
@@ -0,0 +1,66 @@
+use lingo_morph::{
+    context::ProcessingFailed,
+    processors::{just, none_of, one_of, range, satisfy},
+    source::BoxedSlice,
+    Processor,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+enum Op {
+    Push(u8),
+    Pop,
+    Add,
+}
+
+fn ops(ops: &[Op]) -> BoxedSlice<Op> {
+    BoxedSlice::from(ops.to_vec())
+}
+
+#[test]
+fn just_matches_one_item_of_any_type() {
+    let mut processor = just(Op::Pop).zip(just(Op::Add));
+    let diagnosed = processor.with(ops(&[Op::Pop, Op::Add])).process().unwrap();
+    assert_eq!(diagnosed.output, (Op::Pop, Op::Add));
+    let failed = processor.with(ops(&[Op::Add])).process().unwrap_err();
+    assert!(matches!(failed, ProcessingFailed::NoReturn));
+}
+
+#[test]
+fn one_of_and_none_of_check_membership() {
+    let mut binary = one_of([Op::Add, Op::Pop]);
+    assert!(binary.with(ops(&[Op::Add])).process().is_ok());
+    assert!(binary.with(ops(&[Op::Push(1)])).process().is_err());
+    let mut other = none_of([Op::Add, Op::Pop]);
+    assert_eq!(
+        other.with(ops(&[Op::Push(1)])).process().unwrap().output,
+        Op::Push(1)
+    );
+    assert!(other.with(ops(&[Op::Pop])).process().is_err());
+}
+
+#[test]
+fn satisfy_takes_a_predicate() {
+    let mut pushes =
+        satisfy(|op: &Op| matches!(op, Op::Push(_))).fold(Vec::new, |mut pushed, op| {
+            pushed.push(op);
+            pushed
+        });
+    let diagnosed = pushes
+        .with(ops(&[Op::Push(1), Op::Push(2), Op::Add]))
+        .process()
+        .unwrap();
+    assert_eq!(diagnosed.output, [Op::Push(1), Op::Push(2)]);
+}
+
+#[test]
+fn range_works_on_bytes() {
+    let mut digits = range(b'0'..=b'9').fold(Vec::new, |mut digits, digit| {
+        digits.push(digit - b'0');
+        digits
+    });
+    let diagnosed = digits
+        .with(BoxedSlice::from(b"123x".to_vec()))
+        .process()
+        .unwrap();
+    assert_eq!(diagnosed.output, [1, 2, 3]);
+}
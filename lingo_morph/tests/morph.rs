@@ -0,0 +1,83 @@
+use std::{cell::RefCell, rc::Rc};
+
+use lingo_morph::{
+    morph::{func, Found, Template},
+    processors::{character, character_range},
+    source::BoxedSlice,
+    trace::Event,
+    Processor,
+};
+
+fn chars(input: &str) -> BoxedSlice<char> {
+    BoxedSlice::from(input.chars().collect::<Vec<_>>())
+}
+
+#[test]
+fn matches_are_replaced_and_the_rest_is_copied() {
+    let mut processor = character('a').zip(character('b'));
+    let morphed = processor.with(chars("xaby ab")).morph("<>").unwrap().output;
+    assert_eq!(morphed, "x<>y <>");
+    let mut digits = character_range('0'..='9').fold(String::new, |mut text, next| {
+        text.push(next);
+        text
+    });
+    let doubled = func(
+        |found: &Found<'_, String>| match found.text.parse::<u32>() {
+            Ok(number) => (number * 2).to_string(),
+            Err(_) => String::new(),
+        },
+    );
+    let morphed = digits.with(chars("x12y")).morph(doubled).unwrap().output;
+    assert_eq!(morphed, "x24y");
+}
+
+#[test]
+fn templates_refer_to_captures() {
+    let mut processor = character('a').zip(character_range('0'..='9'));
+    let morphed = processor
+        .with(chars("a1 a2"))
+        .morph(Template::new("[$2$1]$$"))
+        .unwrap()
+        .output;
+    assert_eq!(morphed, "[1a]$ [2a]$");
+}
+
+#[test]
+fn empty_matches_include_the_end_of_input() {
+    let mut processor = character('a').fold(|| (), |_, _| ());
+    let morphed = processor.with(chars("bc")).morph("-").unwrap().output;
+    assert_eq!(morphed, "-b-c-");
+    let morphed = processor.with(chars("baa")).morph("-").unwrap().output;
+    assert_eq!(morphed, "-b-");
+}
+
+#[test]
+fn matches_are_kept_instead_of_rolled_back() {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let recorded = events.clone();
+    let tracer = Rc::new(RefCell::new(move |event| recorded.borrow_mut().push(event)));
+    let mut processor = character('a').zip(character('b'));
+    let morphed = processor
+        .with(chars("ab"))
+        .tracer(tracer)
+        .morph("x")
+        .unwrap()
+        .output;
+    assert_eq!(morphed, "x");
+    // The attempt at the end of input starts right after the match
+    let entered: Vec<_> = events
+        .borrow()
+        .iter()
+        .filter_map(|event| match event {
+            Event::Enter { offset, .. } => Some(*offset),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(entered, [0, 2]);
+    // Moving on after an attempt isn't a rollback of the processor
+    let rolled_back = events
+        .borrow()
+        .iter()
+        .any(|event| matches!(event, Event::Rollback { .. }));
+    assert!(!rolled_back);
+}
@@ -0,0 +1,111 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+use lingo_morph::{
+    context, done, err,
+    parallel::{Parallel, ParallelError},
+    processed::Processed,
+    processors::{character, character_range},
+    source::Source,
+    try_done, Processor,
+};
+
+#[derive(Debug, PartialEq)]
+struct Negative;
+
+impl Display for Negative {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "negative record")
+    }
+}
+
+impl Error for Negative {}
+
+// A number followed by a semicolon, warning about leading zeros and failing on a minus
+#[derive(Clone)]
+struct Record;
+
+impl Processor<char> for Record {
+    type Output = u32;
+
+    fn process<S>(&mut self, mut given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = char>,
+    {
+        if given.peek() == Some(&'-') {
+            return err(Negative);
+        }
+        if given.peek() == Some(&'0') {
+            context::warn(&mut given, "leading zero");
+        }
+        let mut number = character_range('0'..='9')
+            .fold(|| 0, |value, next| value * 10 + next.to_digit(10).unwrap())
+            .ignore_next(character(';'));
+        let (value, rest) = try_done!(number.process(given));
+        done(value, rest)
+    }
+}
+
+fn input(records: usize) -> Vec<char> {
+    (0..records)
+        .flat_map(|x| format!("{x};").chars().collect::<Vec<_>>())
+        .collect()
+}
+
+#[test]
+fn outputs_keep_the_order_of_the_input() {
+    let input = input(500);
+    let diagnosed = Parallel::new(&input, Record, character(';'))
+        .threads(4)
+        .run()
+        .unwrap();
+    assert_eq!(diagnosed.output, (0..500).collect::<Vec<_>>());
+    // Only the first record starts with a zero
+    assert_eq!(diagnosed.diagnostics.len(), 1);
+}
+
+#[test]
+fn errors_keep_their_type_and_whole_input_offset() {
+    let mut input = input(300);
+    let at = input.len() - 4;
+    input.splice(at..at, "-1;".chars());
+    let error = Parallel::new(&input, Record, character(';'))
+        .threads(3)
+        .run()
+        .unwrap_err();
+    assert_eq!(error.offset(), at);
+    match error {
+        ParallelError::Failed { error, .. } => {
+            assert_eq!(*error.downcast::<Negative>().unwrap(), Negative)
+        }
+        other => panic!("expected the record's error, got {other:?}"),
+    }
+}
+
+#[test]
+fn diagnostics_count_from_the_start_of_the_input() {
+    let text = "1;2;3;04;5;6;7;08;9;";
+    let input = text.chars().collect::<Vec<_>>();
+    let diagnosed = Parallel::new(&input, Record, character(';'))
+        .threads(4)
+        .run()
+        .unwrap();
+    let offsets = diagnosed
+        .diagnostics
+        .iter()
+        .map(|x| x.offset())
+        .collect::<Vec<_>>();
+    assert_eq!(offsets, [6, 15]);
+}
+
+#[test]
+fn records_which_do_not_match_are_reported() {
+    let input = "1;2;x;".chars().collect::<Vec<_>>();
+    let error = Parallel::new(&input, Record, character(';'))
+        .threads(2)
+        .run()
+        .unwrap_err();
+    assert!(matches!(error, ParallelError::Mismatch { offset: 4 }));
+}
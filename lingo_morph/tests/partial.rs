@@ -0,0 +1,157 @@
+use std::{cell::Cell, rc::Rc};
+
+use lingo_morph::{
+    binary::take_bytes,
+    context::{ProcessingFailed, Streaming},
+    encoding::{Decoder, Encoding},
+    lexer::{kind, Lexer},
+    processed::Processed,
+    processors::{any, character, character_range},
+    source::{Borrowed, BoxedSlice, IterSource, Partial, Source},
+    trivia::Trivia,
+    Processor,
+};
+
+fn chars(input: &str) -> BoxedSlice<char> {
+    BoxedSlice::from(input.chars().collect::<Vec<_>>())
+}
+
+fn text() -> impl Processor<char, Output = String> {
+    any().fold(String::new, |mut text, next| {
+        text.push(next);
+        text
+    })
+}
+
+fn is_incomplete<T, C>(processed: Result<T, ProcessingFailed<C>>) -> bool {
+    matches!(processed, Err(ProcessingFailed::Incomplete(_)))
+}
+
+#[test]
+fn split_sequences_wait_for_the_rest() {
+    let bytes = "a€".as_bytes();
+    let mut decoder = Decoder::new(Partial::new(Borrowed::from(&bytes[..3])), Encoding::Utf8);
+    assert_eq!(decoder.next(), Some('a'));
+    assert_eq!(decoder.next(), None);
+    assert!(decoder.is_partial());
+    assert_eq!(decoder.byte_offset(), 1);
+    let decoder = Decoder::new(Partial::new(Borrowed::from(&bytes[..3])), Encoding::Utf8);
+    assert!(is_incomplete(text().with(decoder).process()));
+    // Without more to come the cut sequence is malformed
+    let mut decoder = Decoder::new(Borrowed::from(&bytes[..3]), Encoding::Utf8);
+    assert_eq!(decoder.iter().collect::<String>(), "a\u{fffd}");
+    let units: Vec<u8> = "😀".encode_utf16().flat_map(u16::to_le_bytes).collect();
+    for cut in 1..units.len() {
+        let source = Partial::new(Borrowed::from(&units[..cut]));
+        let mut decoder = Decoder::new(source, Encoding::Utf16Le);
+        assert_eq!(decoder.next(), None);
+        assert_eq!(decoder.byte_offset(), 0);
+    }
+}
+
+#[test]
+fn byte_order_marks_split_across_chunks_wait_for_the_rest() {
+    let bytes = [0xef, 0xbb, 0xbf, b'o', b'k'];
+    for cut in 0..3 {
+        let decoder = Decoder::detect(Partial::new(Borrowed::from(&bytes[..cut])));
+        assert!(is_incomplete(text().with(decoder).process()));
+    }
+    for first in [0xff, 0xfe] {
+        let bytes = [first];
+        let decoder = Decoder::detect(Partial::new(Borrowed::from(&bytes[..])));
+        assert!(is_incomplete(any().with(decoder).process()));
+    }
+    let decoder = Decoder::detect(Partial::new(Borrowed::from(&bytes[..4])));
+    assert_eq!(any().with(decoder).process().unwrap().output, 'o');
+    // Bytes which can't start a mark don't wait for more
+    let mut decoder = Decoder::detect(Partial::new(Borrowed::from(&b"a"[..])));
+    assert_eq!(decoder.next(), Some('a'));
+    assert_eq!(decoder.encoding(), Some(Encoding::Utf8));
+    // Without more to come the cut mark is decoded as it is
+    let mut decoder = Decoder::detect(Borrowed::from(&bytes[..2]));
+    assert_eq!(decoder.iter().collect::<String>(), "\u{fffd}");
+}
+
+#[test]
+fn trivia_at_the_end_of_partial_input_is_incomplete() {
+    let trivia = || Trivia::new().line_comment("//").block_comment("/*", "*/");
+    let word = || character_range('a'..='z').lexeme();
+    for input in ["a  ", "a /", "a /* b *", "a // b"] {
+        let mut processor = word().zip(character('/'));
+        let processed = processor
+            .with(Partial::new(chars(input)))
+            .trivia(trivia())
+            .process();
+        assert!(is_incomplete(processed), "{input:?}");
+    }
+    let mut processor = word().zip(word());
+    let diagnosed = processor
+        .with(Partial::new(chars("a /* b */ c d")))
+        .trivia(trivia())
+        .process();
+    assert_eq!(diagnosed.unwrap().output, ('a', 'c'));
+}
+
+#[test]
+fn wrapping_sources_stay_partial() {
+    let mut processor = character('a').zip(character('b'));
+    let source = IterSource::new("a".chars()).partial(true);
+    assert!(is_incomplete(processor.with(source).process()));
+    let source = IterSource::new("a".chars());
+    assert!(matches!(
+        processor.with(source).process(),
+        Err(ProcessingFailed::NoReturn)
+    ));
+}
+
+#[test]
+fn tokens_cut_by_the_end_of_partial_input_are_held_back() {
+    let word = character_range('a'..='z').fold(|| 'w', |kind, _| kind);
+    let blank = character(' ').fold(|| (), |_, _| ());
+    let mut lexer = Lexer::new().rule(word).skip(blank);
+    let mut tokens = lexer.tokens(Partial::new(chars("ab cd")));
+    assert_eq!(tokens.next().unwrap().text, "ab");
+    assert_eq!(tokens.next(), None);
+    assert!(tokens.is_partial());
+    let mut lexer = Lexer::new().rule(character_range('a'..='z').fold(|| 'w', |kind, _| kind));
+    let tokens = lexer.tokens(Partial::new(chars("ab")));
+    let mut processor = kind('w').zip(kind('w'));
+    assert!(is_incomplete(processor.with(tokens).process()));
+}
+
+// Counts how often the processor gets run
+struct Counted<P>(Rc<Cell<usize>>, P);
+
+impl<P> Processor<u8> for Counted<P>
+where
+    P: Processor<u8>,
+{
+    type Output = P::Output;
+
+    fn process<S>(&mut self, given: S) -> Processed<Self::Output, S>
+    where
+        S: Source<Item = u8>,
+    {
+        self.0.set(self.0.get() + 1);
+        self.1.process(given)
+    }
+}
+
+#[test]
+fn streams_wait_for_the_bytes_they_need() {
+    let runs = Rc::new(Cell::new(0));
+    let mut stream = Streaming::new(Counted(runs.clone(), take_bytes(4)));
+    let mut outputs = Vec::new();
+    for byte in 1..=8 {
+        for output in stream.feed(&[byte]) {
+            outputs.push(output.unwrap());
+        }
+    }
+    assert_eq!(outputs, [Box::from([1, 2, 3, 4]), Box::from([5, 6, 7, 8])]);
+    assert_eq!(runs.get(), 4);
+    // Whatever is left gets processed once more when the input is finished
+    assert!(stream.feed(&[9]).next().is_none());
+    assert_eq!(runs.get(), 5);
+    assert!(stream.finish().next().unwrap().is_err());
+    assert_eq!(runs.get(), 6);
+}
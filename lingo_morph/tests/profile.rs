@@ -0,0 +1,89 @@
+#![cfg(feature = "profiling")]
+
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use lingo_morph::{
+    processors::character,
+    profile::{Profiler, Stats},
+    source::BoxedSlice,
+    Processor,
+};
+
+fn chars(input: &str) -> BoxedSlice<char> {
+    BoxedSlice::from(input.chars().collect::<Vec<_>>())
+}
+
+fn profiled<P>(mut processor: P, input: &str) -> Profiler
+where
+    P: Processor<char>,
+{
+    let profiler = Rc::new(RefCell::new(Profiler::new()));
+    processor
+        .with(chars(input))
+        .tracer(profiler.clone())
+        .process()
+        .unwrap();
+    Rc::try_unwrap(profiler).ok().unwrap().into_inner()
+}
+
+#[test]
+fn calls_and_outcomes_are_counted_per_name() {
+    let pair = character('a').ignore(character('b')).named("pair");
+    let single = character('c').named("single");
+    let profiler = profiled(pair.or(single).fold(|| (), |_, _| ()), "abcab");
+    let pair = profiler.stats("pair").unwrap();
+    assert_eq!((pair.calls, pair.done, pair.mismatches), (4, 2, 2));
+    assert_eq!(pair.consumed, 4);
+    let single = profiler.stats("single").unwrap();
+    assert_eq!((single.calls, single.done, single.mismatches), (2, 1, 1));
+    assert_eq!(single.consumed, 1);
+    assert_eq!(profiler.stats("or").unwrap().done, 3);
+    assert_eq!(profiler.stats("fold").unwrap().calls, 1);
+    assert!(profiler.stats("missing").is_none());
+}
+
+#[test]
+fn rollbacks_are_charged_to_the_processor_backtracking() {
+    let item = character('a').ignore(character('b')).or(character('a'));
+    let profiler = profiled(item.fold(|| (), |_, _| ()), "aab");
+    let ignore = profiler.stats("ignore").unwrap();
+    assert_eq!(ignore.rollbacks, 1);
+    assert_eq!((ignore.calls, ignore.done, ignore.mismatches), (3, 1, 2));
+    assert_eq!(profiler.stats("or").unwrap().rollbacks, 0);
+}
+
+#[test]
+fn nested_time_is_not_counted_twice() {
+    let item = character('a').named("item");
+    let profiler = profiled(item.fold(|| (), |_, _| ()).named("outer"), "aaaa");
+    let outer = profiler.stats("outer").unwrap();
+    let fold = profiler.stats("fold").unwrap();
+    assert!(outer.self_time <= outer.time);
+    assert!(fold.time <= outer.time);
+    assert!(fold.self_time <= fold.time);
+    let total = profiler
+        .ranked()
+        .iter()
+        .map(|(_, stats)| stats.self_time)
+        .sum::<Duration>();
+    assert!(outer.time >= total);
+}
+
+#[test]
+fn reports_rank_processors_by_their_own_time() {
+    let item = character('a').named("item");
+    let profiler = profiled(item.fold(|| (), |_, _| ()), "aaa");
+    let ranked = profiler.ranked();
+    let names = ranked.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+    assert_eq!(names.len(), 2);
+    assert!(names.contains(&"item") && names.contains(&"fold"));
+    let times = ranked.iter().map(|(_, stats)| stats.self_time);
+    assert!(times.clone().zip(times.skip(1)).all(|(a, b)| a >= b));
+    let report = profiler.report(1);
+    let lines = report.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("name"));
+    assert!(lines[1].starts_with(names[0]));
+    let item: Stats = *profiler.stats("item").unwrap();
+    assert_eq!((item.calls, item.done, item.mismatches), (4, 3, 1));
+}
@@ -0,0 +1,55 @@
+use lingo_morph::{
+    processors::character,
+    source::{BoxedSlice, Span},
+    Processor,
+};
+
+fn chars(input: &str) -> BoxedSlice<char> {
+    BoxedSlice::from(input.chars().collect::<Vec<_>>())
+}
+
+fn spans<P>(processor: &mut P, input: &str, overlapping: bool) -> Vec<(usize, usize)>
+where
+    P: Processor<char>,
+{
+    let with = processor.with(chars(input));
+    let found = if overlapping {
+        with.find_overlapping()
+    } else {
+        with.find_iter()
+    };
+    found
+        .map(|found| {
+            let Span { start, end } = found.unwrap().span;
+            (start, end)
+        })
+        .collect()
+}
+
+#[test]
+fn matches_are_found_between_other_input() {
+    let mut processor = character('a').zip(character('b'));
+    assert_eq!(spans(&mut processor, "xabyyab", false), [(1, 3), (5, 7)]);
+    let outputs: Vec<_> = processor
+        .with(chars("abab"))
+        .matches()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(outputs, [('a', 'b'), ('a', 'b')]);
+}
+
+#[test]
+fn overlapping_matches_start_at_every_position() {
+    let mut processor = character('a').zip(character('a'));
+    assert_eq!(spans(&mut processor, "aaa", false), [(0, 2)]);
+    assert_eq!(spans(&mut processor, "aaa", true), [(0, 2), (1, 3)]);
+}
+
+#[test]
+fn empty_matches_include_the_end_of_input() {
+    let mut repeated = character('a').fold(|| (), |_, _| ());
+    assert_eq!(spans(&mut repeated, "bc", false), [(0, 0), (1, 1), (2, 2)]);
+    assert_eq!(spans(&mut repeated, "", false), [(0, 0)]);
+    // An empty match right after a match is skipped, also at the end
+    assert_eq!(spans(&mut repeated, "baa", false), [(0, 0), (1, 3)]);
+}
@@ -0,0 +1,53 @@
+use lingo_morph::{
+    processors::character,
+    source::{BoxedSlice, Span},
+    Processor,
+};
+
+fn chars(input: &str) -> BoxedSlice<char> {
+    BoxedSlice::from(input.chars().collect::<Vec<_>>())
+}
+
+#[test]
+fn offsets_map_both_ways() {
+    let mut processor = character('a');
+    let (morphed, map) = processor
+        .with(chars("xay\nza"))
+        .morph_mapped("AAA")
+        .unwrap()
+        .output;
+    assert_eq!(morphed, "xAAAy\nzAAA");
+    assert_eq!(map.original(0), Some(Span::new(0, 1)));
+    assert_eq!(map.original(2), Some(Span::new(1, 2)));
+    assert_eq!(map.original(4), Some(Span::new(2, 3)));
+    assert_eq!(map.generated(1), Some(Span::new(1, 4)));
+    assert_eq!(map.generated(3), Some(Span::new(5, 6)));
+    assert_eq!(map.original(11), None);
+    let position = map.generated_position(5).unwrap();
+    assert_eq!((position.line, position.column), (2, 2));
+    let position = map.original_position(7).unwrap();
+    assert_eq!((position.line, position.column), (2, 2));
+}
+
+#[test]
+fn json_columns_count_utf16_code_units() {
+    let mut processor = character('a');
+    let (morphed, map) = processor
+        .with(chars("😀a"))
+        .morph_mapped("bb")
+        .unwrap()
+        .output;
+    assert_eq!(morphed, "😀bb");
+    assert_eq!(
+        map.to_json("out.txt", "in.txt"),
+        r#"{"version":3,"file":"out.txt","sources":["in.txt"],"names":[],"mappings":"AAAA,EAAE"}"#
+    );
+    let (_, map) = processor
+        .with(chars("a\nb"))
+        .morph_mapped("x")
+        .unwrap()
+        .output;
+    assert!(map
+        .to_json("out", "in")
+        .contains(r#""mappings":"AAAA,CAAC;AACD""#));
+}
@@ -0,0 +1,99 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use lingo_morph::{
+    context::{ProcessingFailed, State},
+    processors::{any, character},
+    source::BoxedSlice,
+    Processor,
+};
+
+fn chars(input: &str) -> BoxedSlice<char> {
+    BoxedSlice::from(input.chars().collect::<Vec<_>>())
+}
+
+#[test]
+fn state_is_threaded_through_processing() {
+    let mut processor = any()
+        .map_with_state(|next: char, seen: &mut Vec<char>| seen.push(next))
+        .fold(|| (), |_, _| ());
+    let mut seen: Vec<char> = Vec::new();
+    processor
+        .with(chars("abc"))
+        .process_with_state(&mut seen)
+        .unwrap();
+    assert_eq!(seen, ['a', 'b', 'c']);
+}
+
+#[test]
+fn state_changes_are_undone_on_roll_back() {
+    let counted = any().map_with_state(|next: char, count: &mut usize| {
+        *count += 1;
+        next
+    });
+    let mut processor = counted.ignore(character('x')).or(any());
+    let mut count = 0usize;
+    let diagnosed = processor
+        .with(chars("ab"))
+        .process_with_state(&mut count)
+        .unwrap();
+    assert_eq!(diagnosed.output, 'a');
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn verify_with_state_can_reject() {
+    let mut processor = any()
+        .verify_with_state(|next: &char, allowed: &Vec<char>| allowed.contains(next))
+        .or(character('b').replace('?'));
+    let mut allowed = vec!['a'];
+    let diagnosed = processor
+        .with(chars("b"))
+        .process_with_state(&mut allowed)
+        .unwrap();
+    assert_eq!(diagnosed.output, '?');
+}
+
+#[test]
+fn missing_state_is_an_error() {
+    let mut processor = any().map_with_state(|next: char, _: &mut usize| next);
+    match processor.with(chars("a")).process() {
+        Err(ProcessingFailed::DuringProcessing(error)) => {
+            assert!(error.to_string().contains("usize"))
+        }
+        other => panic!("expected a missing state error, got {other:?}"),
+    }
+}
+
+static CLONES: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Default)]
+struct Counted(Vec<u8>);
+
+impl Clone for Counted {
+    fn clone(&self) -> Self {
+        CLONES.fetch_add(1, Ordering::Relaxed);
+        Self(self.0.clone())
+    }
+}
+
+#[test]
+fn snapshots_do_not_clone_untouched_state() {
+    // Each ignore takes a snapshot, but only the end touches the state
+    let touched = character('z').map_with_state(|next: char, _: &mut Counted| next);
+    let mut processor = any()
+        .ignore(any())
+        .ignore(any())
+        .ignore(any())
+        .ignore(touched);
+    let mut state = Counted(vec![0; 1024]);
+    let diagnosed = processor
+        .with(chars("abcdz"))
+        .process_with_state(&mut state)
+        .unwrap();
+    assert_eq!(diagnosed.output, 'z');
+    assert_eq!(CLONES.load(Ordering::Relaxed), 1);
+    let saved = state.save();
+    let mut restored = Counted::default();
+    restored.restore(&*saved);
+    assert_eq!(restored.0.len(), 1024);
+}
@@ -0,0 +1,131 @@
+use std::{cell::RefCell, rc::Rc};
+
+use lingo_morph::{
+    binary::{length_prefixed, take_bytes, uleb128},
+    cst::SyntaxKind,
+    indent::indented_block,
+    processors::{character, character_range},
+    source::{BoxedSlice, Located},
+    trace::{Event, Outcome, Recorder, Step},
+    trivia::Trivia,
+    Processor,
+};
+
+fn chars(input: &str) -> BoxedSlice<char> {
+    BoxedSlice::from(input.chars().collect::<Vec<_>>())
+}
+
+type Events = Rc<RefCell<Vec<Event>>>;
+
+fn tracer(events: &Events) -> Rc<RefCell<impl FnMut(Event)>> {
+    let recorded = events.clone();
+    Rc::new(RefCell::new(move |event| recorded.borrow_mut().push(event)))
+}
+
+fn entered(events: &Events) -> Vec<&'static str> {
+    let events = events.borrow();
+    let names = events.iter().filter_map(|event| match event {
+        Event::Enter { name, .. } => Some(*name),
+        _ => None,
+    });
+    names.collect()
+}
+
+#[test]
+fn trivia_and_tree_combinators_are_traced() {
+    let events = Events::default();
+    let mut processor = character('a')
+        .token(SyntaxKind(1))
+        .lexeme()
+        .padded()
+        .node(SyntaxKind(2));
+    processor
+        .with(chars(" a "))
+        .trivia(Trivia::new().whitespace(|x| x.is_whitespace()))
+        .tracer(tracer(&events))
+        .process()
+        .unwrap();
+    assert_eq!(entered(&events), ["node", "padded", "lexeme", "token"]);
+    let exits = events
+        .borrow()
+        .iter()
+        .filter(|event| matches!(event, Event::Exit { outcome, .. } if *outcome == Outcome::Done))
+        .count();
+    assert_eq!(exits, 4);
+}
+
+#[test]
+fn indented_blocks_are_traced() {
+    let events = Events::default();
+    let word = character_range('a'..='z').fold(|| (), |_, _| ());
+    let mut processor = character(':').ignore(indented_block(word));
+    processor
+        .with(Located::new(chars(":\n  ab\n  c")))
+        .tracer(tracer(&events))
+        .process()
+        .unwrap();
+    let entered = entered(&events);
+    assert_eq!(entered[..2], ["ignore", "indented_block"]);
+}
+
+#[test]
+fn length_prefixed_bodies_are_traced() {
+    let events = Events::default();
+    let mut processor = length_prefixed(uleb128(), take_bytes(2));
+    processor
+        .with(BoxedSlice::from(vec![2, 7, 8]))
+        .tracer(tracer(&events))
+        .process()
+        .unwrap();
+    assert_eq!(entered(&events), ["length_prefixed"]);
+}
+
+#[test]
+fn choices_record_the_alternative_they_took() {
+    let recorder = Rc::new(RefCell::new(Recorder::new()));
+    // Neither leaf is traced, the choice tells which one matched itself
+    let mut processor = character('a').or(character('b')).zip(character('c'));
+    processor
+        .with(chars("bc"))
+        .tracer(recorder.clone())
+        .process()
+        .unwrap();
+    let recorder = recorder.borrow();
+    let [Step::Attempt(zip)] = recorder.steps() else {
+        panic!("expected a single attempt");
+    };
+    assert_eq!(zip.taken(), None);
+    let [Step::Attempt(or)] = zip.steps() else {
+        panic!("expected the choice inside the zip");
+    };
+    assert_eq!(or.taken(), Some(2));
+    assert_eq!(
+        recorder.to_tree(),
+        "zip 0..2 done\n  or 0..1 done, took alternative 2\n"
+    );
+    assert!(recorder
+        .to_json()
+        .contains("\"name\":\"or\",\"start\":0,\"end\":1,\"outcome\":\"done\",\"taken\":2"));
+    assert!(recorder
+        .to_json()
+        .starts_with("[{\"name\":\"zip\",\"start\":0,\"end\":2,\"outcome\":\"done\",\"steps\":"));
+}
+
+#[test]
+fn nested_choices_each_count_their_own_alternatives() {
+    let recorder = Rc::new(RefCell::new(Recorder::new()));
+    let mut processor = character('a')
+        .ignore(character('x'))
+        .or(character('a'))
+        .or(character('b'));
+    processor
+        .with(chars("a"))
+        .tracer(recorder.clone())
+        .process()
+        .unwrap();
+    let tree = recorder.borrow().to_tree();
+    assert!(
+        tree.starts_with("or 0..1 done, took alternative 1\n  or 0..1 done, took alternative 2\n"),
+        "{tree}"
+    );
+}
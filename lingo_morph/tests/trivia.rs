@@ -0,0 +1,121 @@
+use lingo_morph::{
+    context::{Limits, ProcessingFailed},
+    processors::{character, character_range},
+    source::BoxedSlice,
+    trivia::{trivia, Trivia, TriviaKind, UnterminatedComment},
+    Processor,
+};
+
+fn chars(input: &str) -> BoxedSlice<char> {
+    BoxedSlice::from(input.chars().collect::<Vec<_>>())
+}
+
+fn word() -> impl Processor<char, Output = String> {
+    character_range('a'..='z').fold(String::new, |mut word, next| {
+        word.push(next);
+        word
+    })
+}
+
+fn commented() -> Trivia {
+    Trivia::new().line_comment("//").block_comment("/*", "*/")
+}
+
+#[test]
+fn padded_skips_whitespace_on_both_sides() {
+    let mut processor = word().padded().zip(word().padded());
+    let diagnosed = processor.with(chars("  one \n two  ")).process().unwrap();
+    assert_eq!(diagnosed.output, ("one".into(), "two".into()));
+}
+
+#[test]
+fn comments_are_skipped_once_configured() {
+    let mut processor = word().lexeme().zip(word().lexeme());
+    let diagnosed = processor
+        .with(chars("one // rest of line\n /* block */two"))
+        .trivia(commented())
+        .process()
+        .unwrap();
+    assert_eq!(diagnosed.output, ("one".into(), "two".into()));
+    // Without the configuration only whitespace is trivia
+    let diagnosed = processor.with(chars("one // two")).process().unwrap();
+    assert_eq!(diagnosed.output.1, "");
+}
+
+#[test]
+fn captured_lexemes_keep_their_trivia() {
+    let mut processor = word().lexeme_captured();
+    let diagnosed = processor
+        .with(chars("one /* x */\t// y"))
+        .trivia(commented())
+        .process()
+        .unwrap();
+    let (word, pieces) = diagnosed.output;
+    assert_eq!(word, "one");
+    let pieces: Vec<_> = pieces
+        .iter()
+        .map(|piece| (piece.kind, piece.text.as_str()))
+        .collect();
+    assert_eq!(
+        pieces,
+        [
+            (TriviaKind::Whitespace, " "),
+            (TriviaKind::BlockComment, "/* x */"),
+            (TriviaKind::Whitespace, "\t"),
+            (TriviaKind::LineComment, "// y"),
+        ]
+    );
+}
+
+#[test]
+fn block_comments_nest_when_asked_to() {
+    let input = "/* a /* b */ c */x";
+    let mut processor = trivia().ignore(character('x'));
+    let nested = commented().nested(true);
+    assert!(processor
+        .with(chars(input))
+        .trivia(nested)
+        .process()
+        .is_ok());
+    let failed = processor
+        .with(chars(input))
+        .trivia(commented())
+        .process()
+        .unwrap_err();
+    assert!(matches!(failed, ProcessingFailed::NoReturn));
+}
+
+#[test]
+fn unterminated_comments_are_errors() {
+    let mut processor = trivia();
+    let failed = processor
+        .with(chars("/* open"))
+        .trivia(commented())
+        .process()
+        .unwrap_err();
+    match failed {
+        ProcessingFailed::DuringProcessing(error) => {
+            assert!(error.is::<UnterminatedComment>())
+        }
+        other => panic!("expected an unterminated comment, got {other:?}"),
+    }
+}
+
+#[test]
+fn padded_rolls_back_skipped_trivia_on_mismatch() {
+    let mut processor = character('x').padded().or(character(' ').map(|_| '_'));
+    let diagnosed = processor.with(chars(" y")).process().unwrap();
+    assert_eq!(diagnosed.output, '_');
+}
+
+#[test]
+fn looking_for_comment_ends_is_not_a_rollback() {
+    let mut processor = word().padded();
+    let diagnosed = processor
+        .with(chars("/* a * b ** c */ word"))
+        .trivia(commented())
+        .limits(Limits::new().rollbacks(0))
+        .process()
+        .unwrap();
+    assert_eq!(diagnosed.output, "word");
+}
@@ -0,0 +1,81 @@
+#![cfg(feature = "unicode")]
+
+use lingo_morph::{
+    processors::{any, character},
+    source::{BoxedSlice, Partial, Source},
+    unicode::{normalize, Form, GraphemeClusters, Graphemes, Normalized},
+    Processor,
+};
+
+fn chars(input: &str) -> BoxedSlice<char> {
+    BoxedSlice::from(input.chars().collect::<Vec<_>>())
+}
+
+#[test]
+fn graphemes_count_user_perceived_characters() {
+    let text = "e\u{301}👍🏽🇩🇪x";
+    let mut processor = any::<&str>().fold(Vec::new, |mut clusters, cluster| {
+        clusters.push(cluster);
+        clusters
+    });
+    let diagnosed = processor.with(Graphemes::from(text)).process().unwrap();
+    assert_eq!(diagnosed.output, ["e\u{301}", "👍🏽", "🇩🇪", "x"]);
+}
+
+#[test]
+fn clusters_are_read_from_any_char_source() {
+    let mut source = GraphemeClusters::new(chars("a\u{308}\r\nb"));
+    assert_eq!(source.next().as_deref(), Some("a\u{308}"));
+    assert_eq!(source.next().as_deref(), Some("\r\n"));
+    assert_eq!(source.position().unwrap().line, 2);
+    assert_eq!(source.next().as_deref(), Some("b"));
+    assert_eq!(source.next(), None);
+}
+
+#[test]
+fn normalization_composes_with_processors() {
+    let mut processor = character('é').zip(character('x'));
+    let source = Normalized::new(chars("e\u{301}x"), Form::Nfc);
+    assert!(processor.with(source).process().is_ok());
+    let mut source = Normalized::new(chars("éx"), Form::Nfd);
+    assert_eq!(source.iter().collect::<String>(), "e\u{301}x");
+    let mut source = Normalized::new(chars("ﬁ①"), Form::Nfkc);
+    assert_eq!(source.iter().collect::<String>(), "fi1");
+}
+
+#[test]
+fn normalized_sources_roll_back_inside_a_segment() {
+    let mut source = Normalized::new(chars("\u{1e9b}\u{323}a"), Form::Nfd);
+    let expected = normalize("\u{1e9b}\u{323}a", Form::Nfd);
+    let first = source.next();
+    let snapshot = source.snapshot();
+    let rest: String = source.iter().collect();
+    source.roll_back(snapshot).unwrap();
+    assert_eq!(source.iter().collect::<String>(), rest);
+    assert_eq!(format!("{}{rest}", first.unwrap()), expected);
+}
+
+#[test]
+fn partial_sources_wait_for_marks_to_arrive() {
+    let mut source = Normalized::new(Partial::new(chars("ae")), Form::Nfc);
+    assert_eq!(source.next(), Some('a'));
+    // An accent might still follow the e
+    assert_eq!(source.next(), None);
+    assert!(source.is_partial());
+    let mut clusters = GraphemeClusters::new(Partial::new(chars("e")));
+    assert_eq!(clusters.next(), None);
+    let mut graphemes = Graphemes::from("ae").partial(true);
+    assert_eq!(graphemes.next(), Some("a"));
+    assert_eq!(graphemes.next(), None);
+    assert!(graphemes.is_partial());
+}
+
+#[test]
+fn clusters_can_be_changed_through_peek_mut() {
+    let mut source = Graphemes::from("ab");
+    *source.peek_mut().unwrap() = "z";
+    let snapshot = source.snapshot();
+    assert_eq!(source.next(), Some("z"));
+    source.roll_back(snapshot).unwrap();
+    assert_eq!(source.iter().collect::<String>(), "zb");
+}
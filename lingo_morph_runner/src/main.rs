@@ -1,12 +1,18 @@
-use std::fmt::Debug;
+use std::{cell::RefCell, env, fmt::Debug, fs, process, rc::Rc};
 
 use lingo_morph::{
     processed::Processed,
-    processors::{any, character, constant_with, digit_range},
+    processors::{any, constant_with, digit_range},
     source::{Source, BoxedSlice},
+    trace::Recorder,
     Processor,
 };
 
+const INPUT: &str = "hello_world 50123";
+
+const USAGE: &str = "usage: lingo_morph_runner [debug [--json | --html] [--output FILE] [INPUT]]
+       lingo_morph_runner profile [--top N] [INPUT] (built with the profiling feature)";
+
 #[allow(unused)]
 #[derive(Debug)]
 struct ParseThis {
@@ -61,45 +67,164 @@ where
 }
 
 fn create_parse_this() -> impl Processor<char, Output = ParseThis> {
-    let str_parser = any::<char>().take(11).fold(
-        || String::new(),
-        |mut str, x| {
+    let str_parser = any::<char>()
+        .take(11)
+        .fold(String::new, |mut str, x| {
             str.push(x);
             str
-        },
-    );
-    let u32_parser = digit_range(..).unwrap().map(|x| x as u32).fold(
-        || 0,
-        |current, x| {
-            if current == 0 {
-                x
-            } else {
-                current * 10 + x
-            }
-        },
-    );
-    constant_with(|| ParseThisBuilder::default())
+        })
+        .named("string");
+    let u32_parser = digit_range(..)
+        .unwrap()
+        .map(|x| x as u32)
+        .fold(
+            || 0,
+            |current, x| {
+                if current == 0 {
+                    x
+                } else {
+                    current * 10 + x
+                }
+            },
+        )
+        .named("number");
+    constant_with(ParseThisBuilder::default)
         .zip(str_parser)
         .map(|(mut builder, str)| {
             builder.some_string(str);
             builder
         })
-        .ignore_next(character(' '))
+        .lexeme()
         .zip(u32_parser)
         .map(|(mut builder, x)| {
             builder.some_u32(x);
             builder
         })
         .map(|builder| builder.build().unwrap())
+        .named("parse_this")
 }
 
 fn main() {
-    let src_code: Vec<char> = "hello_world 50123".chars().collect();
+    let mut args = env::args().skip(1);
+    match args.next().as_deref() {
+        Some("debug") => debug(args),
+        #[cfg(feature = "profiling")]
+        Some("profile") => profile(args),
+        Some(_) => {
+            eprintln!("{USAGE}");
+            process::exit(2);
+        }
+        None => run(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Tree,
+    Json,
+    Html,
+}
+
+// Processes the input once more, recording every attempt on the way
+fn debug<A>(mut args: A)
+where
+    A: Iterator<Item = String>,
+{
+    let mut format = Format::Tree;
+    let mut output = None;
+    let mut input = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => format = Format::Json,
+            "--html" => format = Format::Html,
+            "--output" => output = args.next(),
+            _ if input.is_none() && !arg.starts_with("--") => input = Some(arg),
+            _ => {
+                eprintln!("{USAGE}");
+                process::exit(2);
+            }
+        }
+    }
+    let source = BoxedSlice::from(
+        input
+            .as_deref()
+            .unwrap_or(INPUT)
+            .chars()
+            .collect::<Vec<_>>(),
+    );
+    let recorder = Rc::new(RefCell::new(Recorder::new()));
+    let mut processor = ConsumeProcessor(create_parse_this(), 5);
+    let processed = processor.with(source).tracer(recorder.clone()).process();
+    let recorder = recorder.borrow();
+    let trace = match format {
+        Format::Tree => recorder.to_tree(),
+        Format::Json => recorder.to_json(),
+        Format::Html => recorder.to_html(),
+    };
+    match output {
+        Some(path) => {
+            if let Err(error) = fs::write(&path, trace) {
+                eprintln!("could not write {path}: {error}");
+                process::exit(1);
+            }
+        }
+        None => println!("{trace}"),
+    }
+    if let Err(error) = processed {
+        eprintln!("{error:#?}");
+    }
+}
+
+#[cfg(feature = "profiling")]
+fn profile<A>(mut args: A)
+where
+    A: Iterator<Item = String>,
+{
+    let mut top = 10;
+    let mut input = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--top" => match args.next().and_then(|x| x.parse().ok()) {
+                Some(amount) => top = amount,
+                None => {
+                    eprintln!("{USAGE}");
+                    process::exit(2);
+                }
+            },
+            _ if input.is_none() && !arg.starts_with("--") => input = Some(arg),
+            _ => {
+                eprintln!("{USAGE}");
+                process::exit(2);
+            }
+        }
+    }
+    let source = BoxedSlice::from(
+        input
+            .as_deref()
+            .unwrap_or(INPUT)
+            .chars()
+            .collect::<Vec<_>>(),
+    );
+    let mut processor = ConsumeProcessor(create_parse_this(), 5);
+    let (processed, profiler) = processor.with(source).profile();
+    print!("{}", profiler.report(top));
+    if let Err(error) = processed {
+        eprintln!("{error:#?}");
+    }
+}
+
+fn run() {
+    let src_code: Vec<char> = INPUT.chars().collect();
     let source = BoxedSlice::from(src_code);
     let mut processor = ConsumeProcessor(create_parse_this(), 5);
     // let mut processor = create_parse_this();
     match processor.with(source).process() {
-        Ok(value) => println!("{value:#?}"),
+        Ok(value) => {
+            for diagnostic in value.diagnostics {
+                println!("{diagnostic}");
+            }
+            println!("{:#?}", value.output);
+        }
         Err(error) => println!("{error:#?}"),
     }
 }